@@ -1,10 +1,22 @@
+// anchor 0.31 生成的 IDL 指令仍调用已弃用的 AccountInfo::realloc
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::ed25519_program;
 use anchor_lang::solana_program::hash::hashv;
-use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
-use anchor_spl::associated_token::get_associated_token_address;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::{
+    TransferFee, TransferFeeAmount, TransferFeeConfig,
+};
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    BaseStateWithExtensions, StateWithExtensions,
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
 
 declare_id!("9xd5Uqy8azvzkZozZjP3NoyQGte7u7swHEWV239GPD6D");
 
@@ -17,26 +29,157 @@ pub mod sharely_contract {
         let config = &mut ctx.accounts.config;
         config.treasury = treasury;
         config.admin = admin;
+        config.pending_admin = Pubkey::default();
+        config.approval_threshold = 1;
+        config.funding_approval_required = false;
+        config.fee_policy = FeePolicy::default();
+        config.fee_upfront = false;
+        config.close_grace_period = 0;
+        config.roles = Vec::new();
         emit!(Initialized {
             admin: ctx.accounts.admin.key(),
-            treasury: treasury,
+            treasury,
         });
         Ok(())
     }
 
-    pub fn change_admin(ctx: Context<ChangeAdmin>, new_admin: Pubkey) -> Result<()> {
+    // 两步移交管理员：当前 admin 提名，新 admin 签名接受后才生效
+    pub fn propose_admin(ctx: Context<ChangeAdmin>, new_admin: Pubkey) -> Result<()> {
+        require!(
+            new_admin != Pubkey::default(),
+            SharelyError::InvalidArgument
+        );
         let config = &mut ctx.accounts.config;
+        config.pending_admin = new_admin;
+        emit!(AdminProposed {
+            current_admin: config.admin,
+            proposed_admin: new_admin,
+        });
+        Ok(())
+    }
 
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.pending_admin != Pubkey::default(),
+            SharelyError::NoPendingAdmin
+        );
         require_keys_eq!(
-            ctx.accounts.signer.key(),
-            config.admin,
+            ctx.accounts.pending_admin.key(),
+            config.pending_admin,
             SharelyError::Unauthorized
         );
 
-        config.admin = new_admin;
+        let old_admin = config.admin;
+        config.admin = config.pending_admin;
+        config.pending_admin = Pubkey::default();
+        // 新 admin 原有的 ApprovalSigner 授权不再单独计数，旧 admin 也不再隐式计入，审批人数不得低于门限
+        require!(
+            config.approver_count() >= config.approval_threshold as usize,
+            SharelyError::InvalidApprovalThreshold
+        );
         emit!(AdminChanged {
-            old_admin: config.admin,
-            new_admin: new_admin
+            old_admin,
+            new_admin: config.admin,
+        });
+        Ok(())
+    }
+
+    pub fn cancel_admin_proposal(ctx: Context<ChangeAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.pending_admin != Pubkey::default(),
+            SharelyError::NoPendingAdmin
+        );
+        let cancelled_admin = config.pending_admin;
+        config.pending_admin = Pubkey::default();
+        emit!(AdminProposalCancelled {
+            admin: config.admin,
+            cancelled_admin,
+        });
+        Ok(())
+    }
+
+    // 授予角色（仅超级管理员 admin 可调用）
+    pub fn grant_role(ctx: Context<GrantRole>, member: Pubkey, role: Role) -> Result<()> {
+        require!(member != Pubkey::default(), SharelyError::InvalidArgument);
+        let config = &mut ctx.accounts.config;
+        require!(
+            !config
+                .roles
+                .iter()
+                .any(|g| g.member == member && g.role == role),
+            SharelyError::RoleAlreadyGranted
+        );
+        require!(
+            config.roles.len() < MAX_ROLE_GRANTS,
+            SharelyError::TooManyRoles
+        );
+        config.roles.push(RoleGrant { member, role });
+        emit!(RoleGranted {
+            admin: ctx.accounts.admin.key(),
+            member,
+            role,
+        });
+        Ok(())
+    }
+
+    // 设置商户审批所需的签名门限（仅超级管理员 admin 可调用）
+    pub fn set_approval_threshold(ctx: Context<ChangeAdmin>, threshold: u8) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            threshold >= 1 && threshold as usize <= config.approver_count(),
+            SharelyError::InvalidApprovalThreshold
+        );
+        config.approval_threshold = threshold;
+        emit!(ApprovalThresholdUpdated {
+            admin: ctx.accounts.admin.key(),
+            threshold,
+            approver_count: config.approver_count() as u8,
+        });
+        Ok(())
+    }
+
+    // 设置商户追加注资是否需要审批（仅超级管理员 admin 可调用）
+    pub fn set_funding_approval_required(ctx: Context<ChangeAdmin>, required: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.funding_approval_required = required;
+        emit!(FundingApprovalRequirementUpdated {
+            admin: ctx.accounts.admin.key(),
+            required,
+        });
+        Ok(())
+    }
+
+    // 设置 admin 代关 quest 前需等待的宽限期（仅 admin 可调用）
+    pub fn set_close_grace_period(ctx: Context<ChangeAdmin>, grace_period: i64) -> Result<()> {
+        require!(grace_period >= 0, SharelyError::InvalidArgument);
+        ctx.accounts.config.close_grace_period = grace_period;
+        emit!(CloseGracePeriodUpdated {
+            admin: ctx.accounts.admin.key(),
+            grace_period,
+        });
+        Ok(())
+    }
+
+    // 撤销角色（仅超级管理员 admin 可调用）
+    pub fn revoke_role(ctx: Context<RevokeRole>, member: Pubkey, role: Role) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let pos = config
+            .roles
+            .iter()
+            .position(|g| g.member == member && g.role == role)
+            .ok_or(SharelyError::RoleNotFound)?;
+        config.roles.remove(pos);
+        // 撤销审批人后剩余审批人数不得低于门限
+        require!(
+            config.approver_count() >= config.approval_threshold as usize,
+            SharelyError::InvalidApprovalThreshold
+        );
+        emit!(RoleRevoked {
+            admin: ctx.accounts.admin.key(),
+            member,
+            role,
         });
         Ok(())
     }
@@ -51,56 +194,94 @@ pub mod sharely_contract {
         require!(total_amount > 0, SharelyError::InvalidAmount);
         // 校验 end_at 是否大于当前时间
 
+        // 不传 mint 即为原生 SOL quest，审批消息中的 mint 须为 Pubkey::default()
+        let (asset, mint_key) = match &ctx.accounts.mint {
+            Some(mint) => (QuestAsset::Token, mint.key()),
+            None => (QuestAsset::NativeSol, Pubkey::default()),
+        };
+
         let config = &mut ctx.accounts.config;
-        // 校验 ed25519 签名，并核对消息体
-        verify_ed25519_signature(&ctx.accounts.instructions, &config.admin, &approval_bytes)?;
-        verify_approval_message(
+        // 校验 ed25519 签名（需 approval_threshold 个不同的 ApprovalSigner），并核对消息体
+        let signers = find_ed25519_signers(&ctx.accounts.instructions, &approval_bytes)?;
+        verify_approval_threshold(config, &signers)?;
+        let approval = verify_approval_message(
             &approval_bytes,
             &config.admin,
             &ctx.accounts.merchant.key(),
-            &ctx.accounts.mint.key(),
+            &mint_key,
             &quest_id,
             &total_amount,
         )?;
 
-        // 手动创建 vault ATA
-        let quest_key = ctx.accounts.quest.key();
-        let vault_seeds = &[
-            b"vault_auth",
-            quest_key.as_ref(),
-            &[ctx.bumps.vault_authority],
-        ];
-        let vault_signer = &[&vault_seeds[..]];
-
-        let create_ata_ctx = CpiContext::new_with_signer(
-            ctx.accounts.associated_token_program.to_account_info(),
-            anchor_spl::associated_token::Create {
-                payer: ctx.accounts.merchant.to_account_info(),
-                associated_token: ctx.accounts.vault.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-                mint: ctx.accounts.mint.to_account_info(),
-                system_program: ctx.accounts.system_program.to_account_info(),
-                token_program: ctx.accounts.token_program.to_account_info(),
-            },
-            vault_signer,
-        );
-        anchor_spl::associated_token::create(create_ata_ctx)?;
+        let (vault_key, received) = match asset {
+            QuestAsset::Token => {
+                let mint = required(&ctx.accounts.mint)?;
+                let vault = required(&ctx.accounts.vault)?;
+                let token_program = required(&ctx.accounts.token_program)?;
 
-        // 商户注资 total_amount 到 vault
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.merchant_source_ata.to_account_info(),
-                to: ctx.accounts.vault.to_account_info(),
-                authority: ctx.accounts.merchant.to_account_info(),
-            },
-        );
-        token::transfer(cpi_ctx, total_amount)?;
+                // 手动创建 vault ATA
+                let quest_key = ctx.accounts.quest.key();
+                let vault_seeds = &[
+                    b"vault_auth",
+                    quest_key.as_ref(),
+                    &[ctx.bumps.vault_authority],
+                ];
+                let vault_signer = &[&vault_seeds[..]];
+
+                let create_ata_ctx = CpiContext::new_with_signer(
+                    required(&ctx.accounts.associated_token_program)?.to_account_info(),
+                    anchor_spl::associated_token::Create {
+                        payer: ctx.accounts.merchant.to_account_info(),
+                        associated_token: vault.to_account_info(),
+                        authority: ctx.accounts.vault_authority.to_account_info(),
+                        mint: mint.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        token_program: token_program.to_account_info(),
+                    },
+                    vault_signer,
+                );
+                anchor_spl::associated_token::create(create_ata_ctx)?;
+
+                // 商户注资 total_amount 到 vault；带 transfer-fee 的 mint 以 vault 实际到账金额记账
+                let cpi_ctx = CpiContext::new(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: required(&ctx.accounts.merchant_source_ata)?.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: vault.to_account_info(),
+                        authority: ctx.accounts.merchant.to_account_info(),
+                    },
+                );
+                token_interface::transfer_checked(cpi_ctx, total_amount, mint.decimals)?;
+                let received = amount_after_transfer_fee(&mint.to_account_info(), total_amount)?;
+                (vault.key(), received)
+            }
+            QuestAsset::NativeSol => {
+                // lamports 直接存放在 vault_authority PDA 上，商户额外垫付免租金额，关闭时一并退回
+                let vault_authority = ctx.accounts.vault_authority.to_account_info();
+                let rent_reserve = Rent::get()?
+                    .minimum_balance(0)
+                    .saturating_sub(vault_authority.lamports());
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.merchant.to_account_info(),
+                            to: vault_authority,
+                        },
+                    ),
+                    total_amount
+                        .checked_add(rent_reserve)
+                        .ok_or(SharelyError::Overflow)?,
+                )?;
+                (ctx.accounts.vault_authority.key(), total_amount)
+            }
+        };
 
         let quest = &mut ctx.accounts.quest;
         quest.quest_id = quest_id;
-        quest.mint = ctx.accounts.mint.key();
-        quest.vault = ctx.accounts.vault.key();
+        quest.mint = mint_key;
+        quest.vault = vault_key;
         quest.vault_authority = ctx.accounts.vault_authority.key();
         quest.merchant = ctx.accounts.merchant.key();
         quest.admin = config.admin;
@@ -111,27 +292,114 @@ pub mod sharely_contract {
         quest.start_at = 0;
         quest.end_at = 0;
         quest.total_amount = total_amount;
-        quest.funded_amount = 0;
-        quest.funded_amount = total_amount;
+        quest.funded_amount = received;
+        // 商户签约的时间窗与手续费上限，activate_quest 不得超出
+        quest.approved_start_at = approval.start_at;
+        quest.approved_end_at = approval.end_at;
+        quest.approved_fee_amount = approval.fee_amount;
+        quest.asset = asset;
+        quest.user_count = 0;
+        // 创建时继承全局手续费策略，之后可通过 set_quest_fee_policy 单独调整
+        quest.fee_policy = config.fee_policy;
 
         emit!(QuestCreated {
             status: quest.status,
             quest: quest.key(),
             quest_id,
             merchant: ctx.accounts.merchant.key(),
-            mint: ctx.accounts.mint.key(),
-            total_amount
+            mint: mint_key,
+            total_amount,
+            approved_start_at: approval.start_at,
+            approved_end_at: approval.end_at,
+            approved_fee_amount: approval.fee_amount,
+            approval_expires_at: approval.expires_at,
+            asset,
+        });
+
+        emit!(VaultFunded {
+            funder: ctx.accounts.merchant.key(),
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            amount: received,
+            funded_amount: quest.funded_amount,
         });
+        Ok(())
+    }
+
+    // 商户为已有 quest 追加注资；config 要求时需附带新的审批签名
+    pub fn fund_quest(
+        ctx: Context<FundQuest>,
+        amount: u64,
+        approval_bytes: Option<Vec<u8>>,
+    ) -> Result<()> {
+        require!(amount > 0, SharelyError::InvalidAmount);
+        let quest = &ctx.accounts.quest;
+        require!(
+            matches!(
+                quest.status,
+                Status::Pending | Status::Active | Status::Paused
+            ),
+            SharelyError::InvalidStatus
+        );
+
+        let config = &ctx.accounts.config;
+        require!(
+            approval_bytes.is_some() || !config.funding_approval_required,
+            SharelyError::InsufficientApprovals
+        );
+        if let Some(approval_bytes) = approval_bytes {
+            let signers = find_ed25519_signers(&ctx.accounts.instructions, &approval_bytes)?;
+            verify_approval_threshold(config, &signers)?;
+            verify_funding_approval(&approval_bytes, &quest.key(), quest, amount)?;
+        }
+
+        let received = match quest.asset {
+            QuestAsset::Token => {
+                let mint = required(&ctx.accounts.mint)?;
+                let cpi_ctx = CpiContext::new(
+                    required(&ctx.accounts.token_program)?.to_account_info(),
+                    TransferChecked {
+                        from: required(&ctx.accounts.merchant_source_ata)?.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: required(&ctx.accounts.vault)?.to_account_info(),
+                        authority: ctx.accounts.merchant.to_account_info(),
+                    },
+                );
+                token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)?;
+                amount_after_transfer_fee(&mint.to_account_info(), amount)?
+            }
+            QuestAsset::NativeSol => {
+                anchor_lang::system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Transfer {
+                            from: ctx.accounts.merchant.to_account_info(),
+                            to: ctx.accounts.vault_authority.to_account_info(),
+                        },
+                    ),
+                    amount,
+                )?;
+                amount
+            }
+        };
+
+        let quest = &mut ctx.accounts.quest;
+        quest.funded_amount = quest
+            .funded_amount
+            .checked_add(received)
+            .ok_or(SharelyError::Overflow)?;
 
         emit!(VaultFunded {
             funder: ctx.accounts.merchant.key(),
             quest: quest.key(),
             quest_id: quest.quest_id,
-            amount: total_amount
+            amount: received,
+            funded_amount: quest.funded_amount,
         });
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn activate_quest(
         ctx: Context<ActivateQuest>,
         merkle_root: [u8; 32],
@@ -139,12 +407,15 @@ pub mod sharely_contract {
         start_at: i64,
         end_at: i64,
         fee_amount: u64,
+        leaf_format: LeafFormat,
+        total_allocated: u64,
     ) -> Result<()> {
-        // 仅管理员可设置 root 并启动
-        require!(
-            ctx.accounts.admin.key() == ctx.accounts.quest.admin,
-            SharelyError::Unauthorized
-        );
+        // 仅 QuestOperator 可设置 root 并启动
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
         require!(end_at > start_at, SharelyError::InvalidArgument);
         // 校验 end_at 是否大于当前时间
         require!(
@@ -154,51 +425,138 @@ pub mod sharely_contract {
         let quest = &mut ctx.accounts.quest;
         // 设置 root（允许在未启动或暂停时，且未发生任何领取）
         require!(quest.claimed_total == 0, SharelyError::InvalidArgument);
+        // 追加阶段后 total_allocated 已包含各阶段额度，不可再重设 phase 0
+        require!(quest.phase_count == 0, SharelyError::InvalidStatus);
         require!(user_count > 0, SharelyError::InvalidArgument);
-        require!(user_count <= 1000000, SharelyError::InvalidArgument); // 限制最大 100 万用户
-                                                                        // fee amount must be greater than 0 and less than total_amount
+
+        // fee amount must not exceed total_amount
+        require!(
+            fee_amount <= quest.total_amount,
+            SharelyError::InvalidFeeAmount
+        );
+        // 时间窗与手续费需在商户签约的审批范围内
+        require!(
+            start_at >= quest.approved_start_at && end_at <= quest.approved_end_at,
+            SharelyError::OutsideApprovedWindow
+        );
         require!(
-            fee_amount >= 0 && fee_amount <= quest.total_amount,
+            fee_amount <= quest.approved_fee_amount,
             SharelyError::InvalidFeeAmount
         );
+        // 已预先收取的手续费无法退回 vault，重设时不可低于已收取金额
+        require!(fee_amount >= quest.fee_paid, SharelyError::InvalidFeeAmount);
+        // vesting quest 需在领取窗口结束前完全解锁
+        require!(
+            quest.claim_mode != ClaimMode::Vesting || end_at >= quest.vesting_end,
+            SharelyError::InvalidArgument
+        );
+        // 默克尔树的空投总额、手续费与 transfer-fee 预留须由已注资金额覆盖
+        require!(total_allocated > 0, SharelyError::InvalidArgument);
+        let payout = total_allocated
+            .checked_add(fee_amount)
+            .ok_or(SharelyError::Overflow)?;
+        // 每个用户一笔领取转账，另加一笔手续费转账
+        let transfer_fee_reserve =
+            quest_transfer_fee_reserve(quest, &ctx.accounts.mint, payout, user_count as u64 + 1)?;
 
         // 扣除 fee_amount 到 admin 账户
         quest.merkle_root = merkle_root;
+        quest.leaf_format = leaf_format;
+        quest.total_allocated = total_allocated;
         quest.version = quest.version.checked_add(1).ok_or(SharelyError::Overflow)?;
         quest.fee_amount = fee_amount;
-        // 位图由 init_if_needed 自动创建，这里只需要更新数据
-        let bitmap_size = (user_count + 7) / 8; // 向上取整到字节
-        let required_space = 8 + 32 + 2 + 4 + 4 + 4 + bitmap_size as usize;
-
-        // 检查空间是否足够，如果不够则重新分配
-        let account_info = ctx.accounts.bitmap_shard.to_account_info();
-        let current_space = account_info.data_len();
-        if required_space > current_space {
-            account_info.resize(required_space)?;
-            let additional_lamports = Rent::get()?.minimum_balance(required_space)
-                - Rent::get()?.minimum_balance(current_space);
-            anchor_lang::system_program::transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    anchor_lang::system_program::Transfer {
-                        from: ctx.accounts.admin.to_account_info(),
-                        to: account_info,
-                    },
-                ),
-                additional_lamports,
-            )?;
-        }
+        quest.transfer_fee_reserve = transfer_fee_reserve;
+        quest.user_count = user_count;
+        require!(
+            quest.committed_amount()? <= quest.funded_amount,
+            SharelyError::AllocationExceeded
+        );
 
         quest.start_at = start_at;
         quest.end_at = end_at;
-        // 启动 quest
-        quest.status = Status::Active;
+        // 启动 quest；已启动且未发生领取时仅重设 root，状态不变
+        if quest.status != Status::Active {
+            transition_status(quest, Status::Active)?;
+        }
+
+        // 开启 fee_upfront 时预收手续费策略保证收取的部分（Flat 为 fee_amount，bps 策略为 min_fee），
+        // 关闭时再按策略补收差额，预收金额不会超过最终手续费
+        let upfront_fee = if ctx.accounts.config.fee_upfront {
+            quest
+                .fee_policy
+                .upfront_fee(fee_amount)
+                .saturating_sub(quest.fee_paid)
+        } else {
+            0
+        };
+        if upfront_fee > 0 {
+            let vault_authority = required(&ctx.accounts.vault_authority)?;
+            let bump = ctx
+                .bumps
+                .vault_authority
+                .ok_or(SharelyError::AssetAccountMismatch)?;
+            let quest_key = quest.key();
+            let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
+            let (recipient, transfer_fee) = match quest.asset {
+                QuestAsset::NativeSol => {
+                    let treasury = required(&ctx.accounts.treasury)?;
+                    transfer_native_from_vault(
+                        &ctx.accounts.system_program,
+                        vault_authority,
+                        treasury.to_account_info(),
+                        signer_seeds,
+                        upfront_fee,
+                    )?;
+                    (treasury.key(), 0)
+                }
+                QuestAsset::Token => {
+                    let token_program = required(&ctx.accounts.token_program)?;
+                    let mint = required(&ctx.accounts.mint)?;
+                    let vault = required(&ctx.accounts.vault)?;
+                    let treasury_ata = required(&ctx.accounts.treasury_ata)?;
+                    // transfer-fee 由 vault 承担，treasury 恰好收到 upfront_fee
+                    let transfer_fee =
+                        transfer_fee_for_receipt(&mint.to_account_info(), upfront_fee)?;
+                    let fee_sent = upfront_fee
+                        .checked_add(transfer_fee)
+                        .ok_or(SharelyError::Overflow)?;
+                    transfer_from_vault(
+                        token_program,
+                        mint,
+                        vault,
+                        vault_authority,
+                        treasury_ata.to_account_info(),
+                        signer_seeds,
+                        fee_sent,
+                    )?;
+                    (treasury_ata.key(), transfer_fee)
+                }
+            };
+            quest.charge_transfer_fee(transfer_fee)?;
+            quest.fee_paid = quest
+                .fee_paid
+                .checked_add(upfront_fee)
+                .ok_or(SharelyError::Overflow)?;
+            emit!(FeeTransferred {
+                quest: quest.key(),
+                quest_id: quest.quest_id,
+                fee_amount: upfront_fee,
+                recipient,
+                fee_policy: quest.fee_policy,
+            });
+        }
 
-        let shard = &mut ctx.accounts.bitmap_shard;
-        shard.user_count = user_count;
-        shard.shard_id = 0;
-        shard.quest = quest.key();
-        shard.bits = vec![0; bitmap_size as usize];
+        // shard 0 由 init_if_needed 自动创建，其余分片通过 init_bitmap_shard 补建
+        let shard_user_count = quest.shard_user_count(0);
+        let bitmap_size = reset_bitmap_shard(
+            &ctx.accounts.bitmap_shard,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+            quest.key(),
+            0,
+            0,
+            shard_user_count,
+        )?;
 
         emit!(QuestActivated {
             status: quest.status,
@@ -208,765 +566,3567 @@ pub mod sharely_contract {
             merkle_root: quest.merkle_root,
             start_at,
             end_at,
-            fee_amount
+            fee_amount,
+            fee_paid: quest.fee_paid,
+            user_count,
+            shard_count: quest.shard_count(),
+            leaf_format,
+            total_allocated,
         });
 
         emit!(BitmapInitialized {
             quest: quest.key(),
             quest_id: quest.quest_id,
-            user_count,
+            user_count: shard_user_count,
             bitmap_size,
+            shard_id: 0,
+            phase_id: 0,
         });
 
         Ok(())
     }
 
-    pub fn pause_quest(ctx: Context<AdminOnQuest>) -> Result<()> {
-        require!(
-            ctx.accounts.admin.key() == ctx.accounts.config.admin,
-            SharelyError::Unauthorized
-        );
+    // 追加领取阶段（如 early-bird 之后的正式、bonus 阶段）：各阶段有独立的名单、时间窗与位图，
+    // 共用 quest 的 vault。phase 0 为 activate_quest 设置的 root，phase_id 从 1 起依次递增
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_quest_phase(
+        ctx: Context<AddQuestPhase>,
+        phase_id: u16,
+        merkle_root: [u8; 32],
+        user_count: u32,
+        start_at: i64,
+        end_at: i64,
+        leaf_format: LeafFormat,
+        total_allocated: u64,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let quest = &mut ctx.accounts.quest;
         require!(
-            ctx.accounts.quest.status == Status::Active,
+            matches!(quest.status, Status::Active | Status::Paused),
             SharelyError::InvalidStatus
         );
-        ctx.accounts.quest.status = Status::Paused;
-        emit!(QuestStatusChanged {
-            quest: ctx.accounts.quest.key(),
-            quest_id: ctx.accounts.quest.quest_id,
-            status: ctx.accounts.quest.status
-        });
-        Ok(())
-    }
-
-    pub fn resume_quest(ctx: Context<AdminOnQuest>) -> Result<()> {
+        // vesting / 累计分发以用户领取记录记账，不支持多阶段
         require!(
-            ctx.accounts.admin.key() == ctx.accounts.config.admin,
-            SharelyError::Unauthorized
+            quest.claim_mode == ClaimMode::Bitmap,
+            SharelyError::ClaimModeMismatch
         );
         require!(
-            ctx.accounts.quest.status == Status::Paused,
-            SharelyError::InvalidStatus
+            phase_id
+                == quest
+                    .phase_count
+                    .checked_add(1)
+                    .ok_or(SharelyError::Overflow)?,
+            SharelyError::InvalidArgument
         );
-        ctx.accounts.quest.status = Status::Active;
-        emit!(QuestStatusChanged {
-            quest: ctx.accounts.quest.key(),
-            quest_id: ctx.accounts.quest.quest_id,
-            status: ctx.accounts.quest.status,
-        });
-        Ok(())
-    }
-
-    pub fn claim(ctx: Context<Claim>, index: u64, amount: u64, proof: Vec<[u8; 32]>) -> Result<()> {
-        let quest = &mut ctx.accounts.quest;
-        require!(quest.status == Status::Active, SharelyError::QuestNotActive);
-        let now_ts = Clock::get()?.unix_timestamp;
-        require!(now_ts >= quest.start_at, SharelyError::InvalidStatus);
-        require!(now_ts <= quest.end_at, SharelyError::InvalidStatus);
-        require!(amount > 0, SharelyError::InvalidAmount);
+        require!(end_at > start_at, SharelyError::InvalidArgument);
         require!(
-            proof.len() as u8 <= MAX_PROOF_NODES,
-            SharelyError::ProofTooLong
+            end_at > Clock::get()?.unix_timestamp,
+            SharelyError::InvalidArgument
         );
-
-        // 验证 mint 地址是否正确
         require!(
-            ctx.accounts.mint.key() == quest.mint,
-            SharelyError::AccountMismatch
+            start_at >= quest.approved_start_at && end_at <= quest.approved_end_at,
+            SharelyError::OutsideApprovedWindow
         );
-
-        // 验证 user_ata 地址是否正确
-        let expected_ata =
-            get_associated_token_address(&ctx.accounts.user.key(), &ctx.accounts.mint.key());
+        require!(user_count > 0, SharelyError::InvalidArgument);
+        // 各阶段额度之和、手续费与 transfer-fee 预留须由已注资金额覆盖
+        require!(total_allocated > 0, SharelyError::InvalidArgument);
+        let phase_reserve = quest_transfer_fee_reserve(
+            quest,
+            &ctx.accounts.mint,
+            total_allocated,
+            user_count as u64,
+        )?;
+        quest.total_allocated = quest
+            .total_allocated
+            .checked_add(total_allocated)
+            .ok_or(SharelyError::Overflow)?;
+        quest.transfer_fee_reserve = quest
+            .transfer_fee_reserve
+            .checked_add(phase_reserve)
+            .ok_or(SharelyError::Overflow)?;
         require!(
-            ctx.accounts.user_ata.key() == expected_ata,
-            SharelyError::AccountMismatch
+            quest.committed_amount()? <= quest.funded_amount,
+            SharelyError::AllocationExceeded
         );
 
-        // 如果 user_ata 账户不存在，创建它
-        if ctx.accounts.user_ata.data_is_empty() {
-            anchor_spl::associated_token::create(CpiContext::new(
-                ctx.accounts.associated_token_program.to_account_info(),
-                anchor_spl::associated_token::Create {
-                    payer: ctx.accounts.user.to_account_info(),
-                    associated_token: ctx.accounts.user_ata.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                    mint: ctx.accounts.mint.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                    token_program: ctx.accounts.token_program.to_account_info(),
-                },
-            ))?;
-        }
+        quest.phase_count = phase_id;
+        quest.phases_end_at = quest.phases_end_at.max(end_at);
 
-        let leaf = leaf_hash(index, ctx.accounts.user.key(), amount);
-        let computed_root = compute_merkle_root_sorted(leaf, &proof);
-        require!(
-            computed_root == quest.merkle_root,
-            SharelyError::InvalidProof
-        );
-        // 检查索引是否在有效范围内
-        require!(
-            index < ctx.accounts.bitmap_shard.user_count as u64,
-            SharelyError::BitmapIndexOutOfRange
-        );
+        let phase = &mut ctx.accounts.phase;
+        phase.quest = quest.key();
+        phase.phase_id = phase_id;
+        phase.merkle_root = merkle_root;
+        phase.leaf_format = leaf_format;
+        phase.start_at = start_at;
+        phase.end_at = end_at;
+        phase.user_count = user_count;
+        phase.total_allocated = total_allocated;
+        phase.claimed_total = 0;
+        phase.payer = ctx.accounts.admin.key();
 
-        // 计算位图位置
-        let byte_index = (index / 8) as usize;
-        let bit_offset = (index % 8) as u8;
-        let bit_mask = 1u8 << bit_offset;
+        // 阶段的 shard 0 随阶段创建，其余分片通过 init_bitmap_shard 补建
+        let shard_user_count = phase.shard_user_count(0);
+        let bitmap_size = reset_bitmap_shard(
+            &ctx.accounts.bitmap_shard,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+            quest.key(),
+            0,
+            phase_id,
+            shard_user_count,
+        )?;
 
-        // 检查是否已领取
-        let shard = &mut ctx.accounts.bitmap_shard;
-        let byte = shard.bits.get(byte_index).copied().unwrap_or(0);
-        require!((byte & bit_mask) == 0, SharelyError::AlreadyClaimed);
-        let mut_byte = &mut shard.bits[byte_index];
-        *mut_byte |= bit_mask;
-        let bump = ctx.bumps.vault_authority;
-        let quest_key = quest.key();
-        let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
-        let signer = [signer_seeds];
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault.to_account_info(),
-                to: ctx.accounts.user_ata.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            },
-            &signer,
-        );
-        token::transfer(cpi_ctx, amount)?;
-        quest.claimed_total = quest
-            .claimed_total
-            .checked_add(amount)
-            .ok_or(SharelyError::Overflow)?;
-        emit!(Claimed {
+        emit!(QuestPhaseAdded {
             quest: quest.key(),
             quest_id: quest.quest_id,
-            user: ctx.accounts.user.key(),
-            index,
-            amount,
-            version: quest.version
+            phase_id,
+            merkle_root,
+            start_at,
+            end_at,
+            user_count,
+            shard_count: phase.shard_count(),
+            leaf_format,
+            total_allocated,
+        });
+        emit!(BitmapInitialized {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            user_count: shard_user_count,
+            bitmap_size,
+            shard_id: 0,
+            phase_id,
         });
         Ok(())
     }
 
-    pub fn close_quest_by_merchant(ctx: Context<CloseQuestByMerchant>) -> Result<()> {
-        require!(
-            ctx.accounts.merchant.key() == ctx.accounts.quest.merchant,
-            SharelyError::Unauthorized
-        );
-
-        let now_ts = Clock::get()?.unix_timestamp;
+    // 补建位图分片：user_count 超过单个分片容量时，分片 1..shard_count 需在领取前逐个创建。
+    // phase_id 非 0 时为对应阶段的位图，需传入阶段账户
+    pub fn init_bitmap_shard(
+        ctx: Context<InitBitmapShard>,
+        shard_id: u16,
+        phase_id: u16,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let quest = &ctx.accounts.quest;
         require!(
-            now_ts > ctx.accounts.quest.end_at,
+            matches!(quest.status, Status::Active | Status::Paused),
             SharelyError::InvalidStatus
         );
+        let shard_user_count = match &ctx.accounts.phase {
+            Some(phase) if phase_id > 0 => phase.shard_user_count(shard_id),
+            None if phase_id == 0 => quest.shard_user_count(shard_id),
+            _ => return err!(SharelyError::AccountMismatch),
+        };
+        require!(shard_user_count > 0, SharelyError::BitmapIndexOutOfRange);
 
-        ctx.accounts.quest.status = Status::Closed;
-
-        // transfer fee amount to treasury, the left amount will be transferred to merchant
-        let fee_amount = ctx.accounts.quest.fee_amount;
-        if fee_amount > 0 {
-            // 使用 vault_authority 作为签名者从 vault 转账到 treasury_ata
-            let bump = ctx.bumps.vault_authority;
-            let quest_key = ctx.accounts.quest.key();
-            let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
-            let signer = [signer_seeds];
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault.to_account_info(),
-                    to: ctx.accounts.treasury_ata.to_account_info(),
-                    authority: ctx.accounts.vault_authority.to_account_info(),
-                },
-                &signer,
-            );
-            token::transfer(cpi_ctx, fee_amount)?;
-            emit!(FeeTransferred {
-                quest: ctx.accounts.quest.key(),
-                quest_id: ctx.accounts.quest.quest_id,
-                fee_amount,
-                recipient: ctx.accounts.treasury_ata.key(),
-            });
-        }
-
-        if ctx.accounts.vault.amount > fee_amount {
-            let amount = ctx.accounts.vault.amount - fee_amount;
-            let bump = ctx.bumps.vault_authority;
-            let quest_key = ctx.accounts.quest.key();
-            let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
-            let signer = [signer_seeds];
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault.to_account_info(),
-                    to: ctx.accounts.destination_ata.to_account_info(),
-                    authority: ctx.accounts.vault_authority.to_account_info(),
-                },
-                &signer,
-            );
-            token::transfer(cpi_ctx, amount)?;
-
-            emit!(QuestClosed {
-                status: ctx.accounts.quest.status,
-                quest: ctx.accounts.quest.key(),
-                quest_id: ctx.accounts.quest.quest_id,
-                remaining_transferred: amount,
-                recipient: ctx.accounts.destination_ata.key(),
-            });
-        }
+        let quest_key = quest.key();
+        let quest_id = quest.quest_id;
+        // 已发生领取后不再清零已有分片，只将头部 user_count 偏小的分片（如重新激活前创建的分片）
+        // 扩容至当前 user_count，保留已有的领取记录
+        let bitmap_size =
+            if quest.claimed_total == 0 || is_uninitialized(ctx.accounts.bitmap_shard.as_ref())? {
+                reset_bitmap_shard(
+                    &ctx.accounts.bitmap_shard,
+                    &ctx.accounts.admin,
+                    &ctx.accounts.system_program,
+                    quest_key,
+                    shard_id,
+                    phase_id,
+                    shard_user_count,
+                )?
+            } else {
+                grow_bitmap_shard(
+                    &ctx.accounts.bitmap_shard,
+                    &ctx.accounts.admin,
+                    &ctx.accounts.system_program,
+                    shard_user_count,
+                )?
+            };
 
+        emit!(BitmapInitialized {
+            quest: quest_key,
+            quest_id,
+            user_count: shard_user_count,
+            bitmap_size,
+            shard_id,
+            phase_id,
+        });
         Ok(())
     }
 
-    // only admin can cancel quest
-    pub fn cancel_quest(ctx: Context<CancelQuest>) -> Result<()> {
+    // 将 quest 设为线性解锁模式：cliff_at 前不可领取，之后按
+    // [vesting_start, vesting_end] 线性解锁。仅可在发生任何领取之前设置
+    pub fn configure_vesting(
+        ctx: Context<AdminOnQuest>,
+        cliff_at: i64,
+        vesting_start: i64,
+        vesting_end: i64,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let quest = &mut ctx.accounts.quest;
         require!(
-            ctx.accounts.admin.key() == ctx.accounts.config.admin,
-            SharelyError::Unauthorized
+            matches!(
+                quest.status,
+                Status::Pending | Status::Active | Status::Paused
+            ),
+            SharelyError::InvalidStatus
         );
+        require!(quest.claimed_total == 0, SharelyError::InvalidStatus);
+        require!(quest.phase_count == 0, SharelyError::ClaimModeMismatch);
         require!(
-            ctx.accounts.quest.status == Status::Pending,
-            SharelyError::InvalidStatus
+            vesting_start < vesting_end && vesting_start <= cliff_at && cliff_at <= vesting_end,
+            SharelyError::InvalidArgument
         );
-        ctx.accounts.quest.status = Status::Cancelled;
-        // transfer vault amount to merchant
+        // 领取窗口结束前需完全解锁
+        let end_at = if quest.status == Status::Pending {
+            quest.approved_end_at
+        } else {
+            quest.end_at
+        };
+        require!(vesting_end <= end_at, SharelyError::OutsideApprovedWindow);
 
-        let amount = ctx.accounts.vault.amount;
-        if amount > 0 {
-            let bump = ctx.bumps.vault_authority;
-            let quest_key = ctx.accounts.quest.key();
-            let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
-            let signer = [signer_seeds];
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault.to_account_info(),
-                    to: ctx.accounts.merchant_ata.to_account_info(),
-                    authority: ctx.accounts.vault_authority.to_account_info(),
-                },
-                &signer,
-            );
-            token::transfer(cpi_ctx, amount)?;
+        quest.claim_mode = ClaimMode::Vesting;
+        quest.cliff_at = cliff_at;
+        quest.vesting_start = vesting_start;
+        quest.vesting_end = vesting_end;
+        emit!(VestingConfigured {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            cliff_at,
+            vesting_start,
+            vesting_end,
+        });
+        Ok(())
+    }
 
-            emit!(QuestCancelled {
-                status: ctx.accounts.quest.status,
-                quest: ctx.accounts.quest.key(),
-                quest_id: ctx.accounts.quest.quest_id,
-                remaining_transferred: amount,
-                recipient: ctx.accounts.quest.merchant,
-            });
+    pub fn pause_quest(ctx: Context<AdminOnQuest>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        transition_status(&mut ctx.accounts.quest, Status::Paused)?;
+        emit!(QuestStatusChanged {
+            quest: ctx.accounts.quest.key(),
+            quest_id: ctx.accounts.quest.quest_id,
+            status: ctx.accounts.quest.status
+        });
+        Ok(())
+    }
+
+    pub fn resume_quest(ctx: Context<AdminOnQuest>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        transition_status(&mut ctx.accounts.quest, Status::Active)?;
+        emit!(QuestStatusChanged {
+            quest: ctx.accounts.quest.key(),
+            quest_id: ctx.accounts.quest.quest_id,
+            status: ctx.accounts.quest.status,
+        });
+        Ok(())
+    }
+
+    // 调整 phase 0 的领取截止时间：可随时延长，未过期时可缩短（不早于当前时间）。
+    // 超出商户审批的时间窗需商户联署，并同步更新 approved_end_at
+    pub fn update_quest_window(ctx: Context<UpdateQuestWindow>, end_at: i64) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let quest = &mut ctx.accounts.quest;
+        require!(
+            matches!(quest.status, Status::Active | Status::Paused),
+            SharelyError::InvalidStatus
+        );
+        let now_ts = Clock::get()?.unix_timestamp;
+        let old_end_at = quest.end_at;
+        require!(end_at > quest.start_at, SharelyError::InvalidArgument);
+        // 新的截止时间不得早于当前时间，因此已过期的 quest 只能延长
+        require!(end_at >= now_ts, SharelyError::InvalidArgument);
+        // vesting quest 需在领取窗口结束前完全解锁
+        require!(
+            quest.claim_mode != ClaimMode::Vesting || end_at >= quest.vesting_end,
+            SharelyError::InvalidArgument
+        );
+        let merchant_signed = ctx.accounts.merchant.is_some();
+        if end_at > quest.approved_end_at {
+            require!(merchant_signed, SharelyError::OutsideApprovedWindow);
+            quest.approved_end_at = end_at;
         }
 
+        quest.end_at = end_at;
+        emit!(QuestWindowUpdated {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            start_at: quest.start_at,
+            old_end_at,
+            end_at,
+            merchant_signed,
+        });
         Ok(())
     }
 
-    // =========================
-    // Query Instructions
-    // =========================
+    // phase_id 为 0 时按 activate_quest 设置的 root 与时间窗领取，否则需传入对应阶段账户
+    pub fn claim(
+        ctx: Context<Claim>,
+        phase_id: u16,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require!(
+            (phase_id == 0) == ctx.accounts.phase.is_none(),
+            SharelyError::AccountMismatch
+        );
+        let user = ctx.accounts.user.to_account_info();
+        let recipient = match &ctx.accounts.recipient {
+            Some(recipient) => recipient.to_account_info(),
+            None => user.clone(),
+        };
+        process_claim(
+            ClaimPayout {
+                quest: &mut ctx.accounts.quest,
+                phase: ctx.accounts.phase.as_mut(),
+                vault_authority: &ctx.accounts.vault_authority,
+                vault_authority_bump: ctx.bumps.vault_authority,
+                vault: &ctx.accounts.vault,
+                mint: &ctx.accounts.mint,
+                recipient_ata: &ctx.accounts.user_ata,
+                ledger: ClaimLedger::Bitmap(&ctx.accounts.bitmap_shard),
+                token_program: &ctx.accounts.token_program,
+                associated_token_program: &ctx.accounts.associated_token_program,
+                system_program: &ctx.accounts.system_program,
+                payer: user.clone(),
+                recipient,
+            },
+            user.key(),
+            index,
+            amount,
+            &proof,
+        )
+    }
 
-    pub fn is_claimed(ctx: Context<IsClaimed>, index: u64) -> Result<bool> {
-        let bitmap_shard = &ctx.accounts.bitmap_shard;
+    // vesting quest 的领取：amount 为叶子中的总分配额，每次领取已解锁但尚未领取的部分，
+    // 已领金额记录在 ["claim", quest, user] 领取记录中，可多次调用
+    pub fn claim_vested(
+        ctx: Context<ClaimWithRecord>,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        claim_with_record(ctx, ClaimMode::Vesting, index, amount, &proof)
+    }
 
-        // 检查索引是否在有效范围内
+    // 累计分发 quest 的领取：叶子中为用户累计应得总额，发放 cumulative_amount 减去已领金额的部分。
+    // root 轮换后用户可凭新 proof 继续领取新增部分
+    pub fn claim_cumulative(
+        ctx: Context<ClaimWithRecord>,
+        index: u64,
+        cumulative_amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        claim_with_record(ctx, ClaimMode::Cumulative, index, cumulative_amount, &proof)
+    }
+
+    // 将 quest 设为累计分发模式，之后可通过 publish_root 随时轮换 root。仅可在发生任何领取之前设置
+    pub fn configure_cumulative(ctx: Context<AdminOnQuest>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let quest = &mut ctx.accounts.quest;
         require!(
-            index < bitmap_shard.user_count as u64,
-            SharelyError::BitmapIndexOutOfRange
+            matches!(
+                quest.status,
+                Status::Pending | Status::Active | Status::Paused
+            ),
+            SharelyError::InvalidStatus
         );
+        require!(quest.claimed_total == 0, SharelyError::InvalidStatus);
+        require!(quest.phase_count == 0, SharelyError::ClaimModeMismatch);
+        quest.claim_mode = ClaimMode::Cumulative;
+        emit!(CumulativeConfigured {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+        });
+        Ok(())
+    }
 
-        let byte_index = (index / 8) as usize;
-        let bit_offset = (index % 8) as u8;
-        let mask = 1u8 << bit_offset;
+    // 累计分发 quest 发布新一轮 root（发生领取后也可调用），version 递增。
+    // 叶子金额为累计值，total_allocated 不得减少，且加上手续费不得超过已注资金额
+    pub fn publish_root(
+        ctx: Context<PublishRoot>,
+        merkle_root: [u8; 32],
+        user_count: u32,
+        total_allocated: u64,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let quest = &mut ctx.accounts.quest;
+        require!(
+            quest.claim_mode == ClaimMode::Cumulative,
+            SharelyError::ClaimModeMismatch
+        );
+        require!(
+            matches!(quest.status, Status::Active | Status::Paused),
+            SharelyError::InvalidStatus
+        );
+        require!(user_count > 0, SharelyError::InvalidArgument);
+        require!(
+            total_allocated >= quest.total_allocated,
+            SharelyError::InvalidArgument
+        );
+        // 新 root 的空投总额、手续费与 transfer-fee 预留须由已注资金额覆盖。
+        // 已承担的 transfer-fee 单独累计，预留按尚未转出的金额重新计算，不低于剩余预留
+        let payout = (total_allocated - quest.claimed_total)
+            .checked_add(quest.fee_amount.saturating_sub(quest.fee_paid))
+            .ok_or(SharelyError::Overflow)?;
+        let transfer_fee_reserve =
+            quest_transfer_fee_reserve(quest, &ctx.accounts.mint, payout, user_count as u64 + 1)?;
 
+        quest.merkle_root = merkle_root;
+        quest.user_count = user_count;
+        quest.total_allocated = total_allocated;
+        quest.transfer_fee_reserve = quest.transfer_fee_reserve.max(transfer_fee_reserve);
         require!(
-            byte_index < bitmap_shard.bits.len(),
-            SharelyError::BitmapIndexOutOfRange
+            quest.committed_amount()? <= quest.funded_amount,
+            SharelyError::AllocationExceeded
+        );
+        quest.version = quest.version.checked_add(1).ok_or(SharelyError::Overflow)?;
+        emit!(RootPublished {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            version: quest.version,
+            merkle_root,
+            user_count,
+            total_allocated,
+        });
+        Ok(())
+    }
+
+    // 一笔指令领取多个 quest。每个条目按顺序从 remaining_accounts 取账户：
+    // [quest(mut), vault_authority(mut), bitmap_shard(mut)]，Token quest 再追加
+    // [vault(mut), mint, user_ata(mut), token_program]；每个条目执行与 claim 相同的校验，仅领取 phase 0
+    pub fn claim_many<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimMany<'info>>,
+        entries: Vec<ClaimEntry>,
+    ) -> Result<()> {
+        require!(
+            !entries.is_empty() && entries.len() <= MAX_CLAIM_BATCH,
+            SharelyError::InvalidArgument
         );
+        let user = ctx.accounts.user.to_account_info();
+        let recipient = match &ctx.accounts.recipient {
+            Some(recipient) => recipient.to_account_info(),
+            None => user.clone(),
+        };
+        let remaining = &mut ctx.remaining_accounts.iter();
+
+        for entry in entries.iter() {
+            let quest_info = next_account_info(remaining)?;
+            require_keys_eq!(quest_info.key(), entry.quest, SharelyError::AccountMismatch);
+            let mut quest = Account::<QuestAccount>::try_from(quest_info)?;
+
+            let vault_authority_info = next_account_info(remaining)?;
+            let (vault_authority_key, vault_authority_bump) =
+                Pubkey::find_program_address(&[b"vault_auth", entry.quest.as_ref()], &crate::ID);
+            require_keys_eq!(
+                vault_authority_info.key(),
+                vault_authority_key,
+                SharelyError::AccountMismatch
+            );
+            let vault_authority = UncheckedAccount::try_from(vault_authority_info);
+
+            // 旧版 Borsh 位图 ["bitmap", quest] 与分片共用 discriminator，须按 PDA 校验分片地址
+            let bitmap_shard_info = next_account_info(remaining)?;
+            let (bitmap_shard_key, _) = Pubkey::find_program_address(
+                &[
+                    b"bitmap",
+                    entry.quest.as_ref(),
+                    &bitmap_shard_seed(bitmap_shard_id(entry.index), 0),
+                ],
+                &crate::ID,
+            );
+            require_keys_eq!(
+                bitmap_shard_info.key(),
+                bitmap_shard_key,
+                SharelyError::AccountMismatch
+            );
+            let bitmap_shard = AccountLoader::<ClaimBitmapShard>::try_from(bitmap_shard_info)?;
+            {
+                let header = bitmap_shard.load()?;
+                require!(
+                    header.quest == entry.quest
+                        && header.phase_id == 0
+                        && header.shard_id == bitmap_shard_id(entry.index),
+                    SharelyError::AccountMismatch
+                );
+            }
 
-        let is_claimed = (bitmap_shard.bits[byte_index] & mask) != 0;
+            let (mut vault, mut mint, mut recipient_ata, mut token_program) =
+                (None, None, None, None);
+            if quest.asset == QuestAsset::Token {
+                let vault_info = next_account_info(remaining)?;
+                require_keys_eq!(vault_info.key(), quest.vault, SharelyError::AccountMismatch);
+                vault = Some(InterfaceAccount::<TokenAccount>::try_from(vault_info)?);
+                let mint_account =
+                    InterfaceAccount::<Mint>::try_from(next_account_info(remaining)?)?;
+                recipient_ata = Some(UncheckedAccount::try_from(next_account_info(remaining)?));
+                let program = Interface::<TokenInterface>::try_from(next_account_info(remaining)?)?;
+                require_keys_eq!(
+                    *mint_account.to_account_info().owner,
+                    program.key(),
+                    SharelyError::AccountMismatch
+                );
+                mint = Some(mint_account);
+                token_program = Some(program);
+            }
 
-        Ok(is_claimed)
+            process_claim(
+                ClaimPayout {
+                    quest: &mut quest,
+                    phase: None,
+                    vault_authority: &vault_authority,
+                    vault_authority_bump,
+                    vault: &vault,
+                    mint: &mint,
+                    recipient_ata: &recipient_ata,
+                    ledger: ClaimLedger::Bitmap(&bitmap_shard),
+                    token_program: &token_program,
+                    associated_token_program: &ctx.accounts.associated_token_program,
+                    system_program: &ctx.accounts.system_program,
+                    payer: user.clone(),
+                    recipient: recipient.clone(),
+                },
+                user.key(),
+                entry.index,
+                entry.amount,
+                &entry.proof,
+            )?;
+            // remaining_accounts 不会被 Anchor 自动写回
+            quest.exit(&crate::ID)?;
+        }
+        Ok(())
     }
 
-    pub fn verify_eligibility(
-        ctx: Context<VerifyEligibility>,
+    // 代付领取：relayer 支付交易费与 ATA 租金，用户以 ed25519 签名授权
+    // (quest, index, amount, recipient, expires_at)，ed25519 指令需紧邻本指令之前；仅领取 phase 0
+    pub fn claim_for(
+        ctx: Context<ClaimFor>,
         index: u64,
         amount: u64,
         proof: Vec<[u8; 32]>,
-    ) -> Result<bool> {
-        let quest = &ctx.accounts.quest;
+        authorization_bytes: Vec<u8>,
+    ) -> Result<()> {
+        let user = ctx.accounts.user.key();
+        verify_claim_authorization(
+            &authorization_bytes,
+            &ctx.accounts.quest.key(),
+            index,
+            amount,
+            &ctx.accounts.recipient.key(),
+        )?;
+        let signers = find_ed25519_signers(&ctx.accounts.instructions, &authorization_bytes)?;
+        require!(signers.contains(&user), SharelyError::InvalidSignature);
 
-        require!(quest.status == Status::Active, SharelyError::QuestNotActive);
+        process_claim(
+            ClaimPayout {
+                quest: &mut ctx.accounts.quest,
+                phase: None,
+                vault_authority: &ctx.accounts.vault_authority,
+                vault_authority_bump: ctx.bumps.vault_authority,
+                vault: &ctx.accounts.vault,
+                mint: &ctx.accounts.mint,
+                recipient_ata: &ctx.accounts.recipient_ata,
+                ledger: ClaimLedger::Bitmap(&ctx.accounts.bitmap_shard),
+                token_program: &ctx.accounts.token_program,
+                associated_token_program: &ctx.accounts.associated_token_program,
+                system_program: &ctx.accounts.system_program,
+                payer: ctx.accounts.relayer.to_account_info(),
+                recipient: ctx.accounts.recipient.to_account_info(),
+            },
+            user,
+            index,
+            amount,
+            &proof,
+        )
+    }
+
+    // 关闭 quest 并回收租金：清空的 vault ATA 与 quest 账户退还商户，
+    // remaining_accounts 中传入的位图分片与阶段账户退还各自的付费方；tombstone 保留审计记录并阻止 quest 被重新创建
+    pub fn close_quest_by_merchant<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseQuestByMerchant<'info>>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.merchant.key() == ctx.accounts.quest.merchant,
+            SharelyError::Unauthorized
+        );
 
-        let leaf = leaf_hash(index, ctx.accounts.user.key(), amount);
-        let computed_root = compute_merkle_root_sorted(leaf, &proof);
+        // 仅 Active / Paused 的 quest 可关闭，且所有阶段的领取窗口已结束
+        let previous = transition_status(&mut ctx.accounts.quest, Status::Closed)?;
+        let now_ts = Clock::get()?.unix_timestamp;
+        require!(
+            now_ts > ctx.accounts.quest.claim_window_end(),
+            SharelyError::InvalidStatus
+        );
+        let fee_amount = if previous.charges_fee() {
+            ctx.accounts.quest.settlement_fee()?
+        } else {
+            0
+        };
 
-        let is_valid = computed_root == quest.merkle_root;
+        let bump = ctx.bumps.vault_authority;
+        let quest_key = ctx.accounts.quest.key();
+        let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
 
-        Ok(is_valid)
+        settle_closed_quest(
+            QuestSettlement {
+                rent: QuestRentReclaim {
+                    quest: &ctx.accounts.quest,
+                    vault_authority: &ctx.accounts.vault_authority,
+                    vault: &ctx.accounts.vault,
+                    mint: &ctx.accounts.mint,
+                    token_program: &ctx.accounts.token_program,
+                    merchant: ctx.accounts.merchant.to_account_info(),
+                    tombstone: &mut ctx.accounts.tombstone,
+                },
+                system_program: &ctx.accounts.system_program,
+                treasury_ata: &ctx.accounts.treasury_ata,
+                treasury: &ctx.accounts.treasury,
+                destination_ata: ctx
+                    .accounts
+                    .destination_ata
+                    .as_ref()
+                    .map(|ata| ata.to_account_info()),
+            },
+            signer_seeds,
+            ctx.remaining_accounts,
+            fee_amount,
+        )
     }
 
-    // 更新 treasury 地址（仅管理员可调用）
-    pub fn update_treasury(ctx: Context<UpdateTreasury>, new_treasury: Pubkey) -> Result<()> {
+    // 商户长期未关闭时，QuestOperator 可在领取窗口结束且超过 config.close_grace_period 后代为关闭：
+    // 手续费转入 treasury，剩余资金退回商户的 ATA（不存在时由 admin 创建），租金回收同 close_quest_by_merchant
+    pub fn close_quest_by_admin<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseQuestByAdmin<'info>>,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+
+        let previous = transition_status(&mut ctx.accounts.quest, Status::Closed)?;
+        let close_after = ctx
+            .accounts
+            .quest
+            .claim_window_end()
+            .checked_add(ctx.accounts.config.close_grace_period)
+            .ok_or(SharelyError::Overflow)?;
         require!(
-            ctx.accounts.admin.key() == ctx.accounts.config.admin,
-            SharelyError::Unauthorized
+            Clock::get()?.unix_timestamp > close_after,
+            SharelyError::GracePeriodNotElapsed
         );
-        ctx.accounts.config.treasury = new_treasury;
-        emit!(TreasuryUpdated {
-            new_treasury,
+        let fee_amount = if previous.charges_fee() {
+            ctx.accounts.quest.settlement_fee()?
+        } else {
+            0
+        };
+
+        let bump = ctx.bumps.vault_authority;
+        let quest_key = ctx.accounts.quest.key();
+        let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
+
+        // 剩余资金只能退回商户自己的 ATA，不存在时由 admin 创建
+        let mut destination_ata = None;
+        if ctx.accounts.quest.asset == QuestAsset::Token {
+            let mint = required(&ctx.accounts.mint)?;
+            let token_program = required(&ctx.accounts.token_program)?;
+            let merchant_ata = required(&ctx.accounts.merchant_ata)?;
+            let expected_ata = get_associated_token_address_with_program_id(
+                &ctx.accounts.merchant.key(),
+                &mint.key(),
+                &token_program.key(),
+            );
+            require!(
+                merchant_ata.key() == expected_ata,
+                SharelyError::AccountMismatch
+            );
+            if merchant_ata.data_is_empty() {
+                anchor_spl::associated_token::create(CpiContext::new(
+                    required(&ctx.accounts.associated_token_program)?.to_account_info(),
+                    anchor_spl::associated_token::Create {
+                        payer: ctx.accounts.admin.to_account_info(),
+                        associated_token: merchant_ata.to_account_info(),
+                        authority: ctx.accounts.merchant.to_account_info(),
+                        mint: mint.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        token_program: token_program.to_account_info(),
+                    },
+                ))?;
+            }
+            destination_ata = Some(merchant_ata.to_account_info());
+        }
+
+        emit!(QuestClosedByAdmin {
+            quest: quest_key,
+            quest_id: ctx.accounts.quest.quest_id,
             admin: ctx.accounts.admin.key(),
+            close_after,
         });
-        Ok(())
-    }
-}
 
-// =========================
-// Accounts
-// =========================
+        settle_closed_quest(
+            QuestSettlement {
+                rent: QuestRentReclaim {
+                    quest: &ctx.accounts.quest,
+                    vault_authority: &ctx.accounts.vault_authority,
+                    vault: &ctx.accounts.vault,
+                    mint: &ctx.accounts.mint,
+                    token_program: &ctx.accounts.token_program,
+                    merchant: ctx.accounts.merchant.to_account_info(),
+                    tombstone: &mut ctx.accounts.tombstone,
+                },
+                system_program: &ctx.accounts.system_program,
+                treasury_ata: &ctx.accounts.treasury_ata,
+                treasury: &ctx.accounts.treasury,
+                destination_ata,
+            },
+            signer_seeds,
+            ctx.remaining_accounts,
+            fee_amount,
+        )
+    }
 
-#[account]
-pub struct QuestAccount {
-    pub quest_id: u64,
-    pub mint: Pubkey,
-    pub vault: Pubkey,
-    pub vault_authority: Pubkey,
-    pub merkle_root: [u8; 32],
-    pub claimed_total: u64,
-    pub status: Status,
-    pub version: u32,
-    pub merchant: Pubkey,
-    pub admin: Pubkey,
-    pub start_at: i64,
-    pub end_at: i64,
-    pub total_amount: u64,
-    pub funded_amount: u64,
-    pub fee_amount: u64,
-}
+    // only quest operator can cancel quest
+    // 退款后与 close_quest_by_merchant 相同地回收 vault ATA、位图分片、阶段账户与 quest 的租金
+    pub fn cancel_quest<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelQuest<'info>>,
+    ) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        // 已发生领取的 quest 需走 emergency_cancel_quest，保留位图以永久阻止重复领取
+        require!(
+            ctx.accounts.quest.claimed_total == 0,
+            SharelyError::QuestHasClaims
+        );
+        let previous = transition_status(&mut ctx.accounts.quest, Status::Cancelled)?;
+        // transfer vault amount to merchant
 
-#[account]
-pub struct ClaimBitmapShard {
-    pub quest: Pubkey,
-    pub shard_id: u16,   // 固定为 0，保持兼容性
-    pub user_count: u32, // 实际用户数量
-    pub bits: Vec<u8>,   // 动态大小的位图
-}
+        let bump = ctx.bumps.vault_authority;
+        let quest_key = ctx.accounts.quest.key();
+        let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
+        let amount = match ctx.accounts.quest.asset {
+            QuestAsset::Token => required(&ctx.accounts.vault)?.amount,
+            // 原生 SOL 连同免租金额一并退回商户
+            QuestAsset::NativeSol => ctx.accounts.vault_authority.lamports(),
+        };
+        if amount > 0 {
+            match ctx.accounts.quest.asset {
+                QuestAsset::Token => transfer_from_vault(
+                    required(&ctx.accounts.token_program)?,
+                    required(&ctx.accounts.mint)?,
+                    required(&ctx.accounts.vault)?,
+                    &ctx.accounts.vault_authority,
+                    required(&ctx.accounts.merchant_ata)?.to_account_info(),
+                    signer_seeds,
+                    amount,
+                )?,
+                QuestAsset::NativeSol => transfer_native_from_vault(
+                    &ctx.accounts.system_program,
+                    &ctx.accounts.vault_authority,
+                    ctx.accounts.merchant.to_account_info(),
+                    signer_seeds,
+                    amount,
+                )?,
+            }
+        }
+        emit_quest_cancelled(&ctx.accounts.quest, previous, amount, false);
 
-#[account]
-pub struct Config {
-    pub admin: Pubkey,
-    pub treasury: Pubkey,
-}
+        reclaim_quest_rent(
+            QuestRentReclaim {
+                quest: &ctx.accounts.quest,
+                vault_authority: &ctx.accounts.vault_authority,
+                vault: &ctx.accounts.vault,
+                mint: &ctx.accounts.mint,
+                token_program: &ctx.accounts.token_program,
+                merchant: ctx.accounts.merchant.to_account_info(),
+                tombstone: &mut ctx.accounts.tombstone,
+            },
+            signer_seeds,
+            ctx.remaining_accounts,
+            0,
+            amount,
+        )
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum Status {
-    Pending,
-    Active,
-    Paused,
-    Closed,
-    Cancelled,
-}
+    // 紧急取消已发生领取的 Active / Paused quest（仅超级管理员 admin 可调用）：
+    // 只退回 vault 中尚未领取的余额，quest 与位图账户保留为 Cancelled 状态，永久阻止后续领取
+    pub fn emergency_cancel_quest(ctx: Context<EmergencyCancelQuest>) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.admin.key(),
+            ctx.accounts.config.admin,
+            SharelyError::Unauthorized
+        );
+        require!(
+            matches!(ctx.accounts.quest.status, Status::Active | Status::Paused),
+            SharelyError::InvalidStatus
+        );
+        let previous = transition_status(&mut ctx.accounts.quest, Status::Cancelled)?;
 
-// =========================
-// Contexts
-// =========================
+        let bump = ctx.bumps.vault_authority;
+        let quest_key = ctx.accounts.quest.key();
+        let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
+        let amount = match ctx.accounts.quest.asset {
+            QuestAsset::Token => {
+                let token_program = required(&ctx.accounts.token_program)?;
+                let mint = required(&ctx.accounts.mint)?;
+                let vault = required(&ctx.accounts.vault)?;
+                let amount = vault.amount;
+                if amount > 0 {
+                    transfer_from_vault(
+                        token_program,
+                        mint,
+                        vault,
+                        &ctx.accounts.vault_authority,
+                        required(&ctx.accounts.merchant_ata)?.to_account_info(),
+                        signer_seeds,
+                        amount,
+                    )?;
+                }
+                // 已清空的 vault ATA 租金退还商户
+                close_vault_ata(
+                    token_program,
+                    mint,
+                    vault,
+                    &ctx.accounts.vault_authority,
+                    ctx.accounts.merchant.to_account_info(),
+                    signer_seeds,
+                )?;
+                amount
+            }
+            // 原生 SOL 连同免租金额一并退回商户
+            QuestAsset::NativeSol => {
+                let amount = ctx.accounts.vault_authority.lamports();
+                if amount > 0 {
+                    transfer_native_from_vault(
+                        &ctx.accounts.system_program,
+                        &ctx.accounts.vault_authority,
+                        ctx.accounts.merchant.to_account_info(),
+                        signer_seeds,
+                        amount,
+                    )?;
+                }
+                amount
+            }
+        };
+        emit_quest_cancelled(&ctx.accounts.quest, previous, amount, true);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    /// CHECK: 全局配置账户，使用固定种子
-    #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 32, // discriminator + admin + treasury
-        seeds = [b"config"],
-        bump
-    )]
-    pub config: Account<'info, Config>,
-    pub system_program: Program<'info, System>,
-}
+    // quest 关闭或取消后（已留下 tombstone）任何人都可关闭其位图分片，租金退还分片记录的付费方。
+    // 分片过多无法在关闭交易中一次传入时，可在关闭后分批回收
+    pub fn close_bitmap_shard(ctx: Context<CloseBitmapShard>) -> Result<()> {
+        let (shard_id, phase_id) = {
+            let header = ctx.accounts.bitmap_shard.load()?;
+            (header.shard_id, header.phase_id)
+        };
+        emit!(BitmapShardClosed {
+            quest: ctx.accounts.quest.key(),
+            quest_id: ctx.accounts.tombstone.quest_id,
+            shard_id,
+            phase_id,
+            payer: ctx.accounts.payer.key(),
+            rent: ctx.accounts.bitmap_shard.to_account_info().lamports(),
+        });
+        Ok(())
+    }
+
+    // 同 close_bitmap_shard，关闭已结束 quest 的阶段账户，租金退还阶段记录的付费方
+    pub fn close_quest_phase(ctx: Context<CloseQuestPhase>) -> Result<()> {
+        emit!(QuestPhaseClosed {
+            quest: ctx.accounts.quest.key(),
+            quest_id: ctx.accounts.tombstone.quest_id,
+            phase_id: ctx.accounts.phase.phase_id,
+            payer: ctx.accounts.payer.key(),
+            rent: ctx.accounts.phase.to_account_info().lamports(),
+        });
+        Ok(())
+    }
+
+    // =========================
+    // Migration Instructions
+    // =========================
+
+    // 将最初版本的 config（仅 admin 与 treasury）扩容到当前布局，新增字段写入与 initialize 相同的默认值。
+    // 旧 config 无法按当前结构反序列化，在此之前其他指令均不可用，需由旧 config 中的 admin 签名
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config_info = ctx.accounts.config.to_account_info();
+        require!(
+            config_info.data_len() == LEGACY_CONFIG_SPACE,
+            SharelyError::AlreadyMigrated
+        );
+        let (admin, treasury) = {
+            let data = config_info.try_borrow_data()?;
+            require!(
+                data.get(..8) == Some(Config::DISCRIMINATOR),
+                SharelyError::AccountMismatch
+            );
+            let admin =
+                Pubkey::try_from(&data[8..40]).map_err(|_| SharelyError::AccountMismatch)?;
+            let treasury =
+                Pubkey::try_from(&data[40..72]).map_err(|_| SharelyError::AccountMismatch)?;
+            (admin, treasury)
+        };
+        require_keys_eq!(ctx.accounts.admin.key(), admin, SharelyError::Unauthorized);
+
+        let space = Config::space(0);
+        config_info.resize(space)?;
+        let additional_lamports = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(config_info.lamports());
+        if additional_lamports > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.admin.to_account_info(),
+                        to: config_info.clone(),
+                    },
+                ),
+                additional_lamports,
+            )?;
+        }
+
+        let config = Config {
+            admin,
+            treasury,
+            pending_admin: Pubkey::default(),
+            approval_threshold: 1,
+            funding_approval_required: false,
+            fee_policy: FeePolicy::default(),
+            fee_upfront: false,
+            close_grace_period: 0,
+            roles: Vec::new(),
+        };
+        let mut data = config_info.try_borrow_mut_data()?;
+        config.try_serialize(&mut &mut data[..])?;
+
+        emit!(ConfigMigrated { admin, treasury });
+        Ok(())
+    }
+
+    // 将旧布局的 quest 扩容到当前布局，新增字段补零。
+    // 最初版本的 quest 以当前时间窗/手续费作为审批值，user_count 取自旧版位图
+    pub fn migrate_quest(ctx: Context<MigrateQuest>) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let quest_info = ctx.accounts.quest.to_account_info();
+        let current_space = quest_info.data_len();
+        {
+            let data = quest_info.try_borrow_data()?;
+            require!(
+                data.get(..8) == Some(QuestAccount::DISCRIMINATOR),
+                SharelyError::AccountMismatch
+            );
+        }
+        require!(
+            current_space < QuestAccount::SPACE,
+            SharelyError::AlreadyMigrated
+        );
+        let legacy_user_count = read_legacy_bitmap(ctx.accounts.legacy_bitmap.as_ref())?
+            .map(|b| b.user_count)
+            .unwrap_or(0);
+
+        quest_info.resize(QuestAccount::SPACE)?;
+        let additional_lamports = Rent::get()?
+            .minimum_balance(QuestAccount::SPACE)
+            .saturating_sub(quest_info.lamports());
+        if additional_lamports > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.admin.to_account_info(),
+                        to: quest_info.clone(),
+                    },
+                ),
+                additional_lamports,
+            )?;
+        }
+
+        let mut data = quest_info.try_borrow_mut_data()?;
+        data[current_space..].fill(0);
+        let mut quest = QuestAccount::try_deserialize(&mut &data[..])?;
+        if current_space == LEGACY_QUEST_SPACE {
+            quest.approved_start_at = quest.start_at;
+            quest.approved_end_at = quest.end_at;
+            quest.approved_fee_amount = quest.fee_amount;
+        }
+        if quest.user_count == 0 {
+            quest.user_count = legacy_user_count;
+        }
+        // 旧 quest 没有承诺空投总额，以注资扣除手续费后的金额为上限
+        if quest.total_allocated == 0 {
+            quest.total_allocated = quest.funded_amount.saturating_sub(quest.fee_amount);
+        }
+        quest.try_serialize(&mut &mut data[..])?;
+
+        emit!(QuestMigrated {
+            quest: quest_info.key(),
+            quest_id: quest.quest_id,
+            user_count: quest.user_count,
+        });
+        Ok(())
+    }
+
+    // 将旧版 Borsh 位图 ["bitmap", quest] 迁移到零拷贝分片。
+    // 每次迁移最后一个尚未迁移的分片并截断旧位图，全部迁移后关闭旧账户，租金退回 admin
+    pub fn migrate_bitmap(ctx: Context<MigrateBitmap>, shard_id: u16) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::QuestOperator,
+        )?;
+        let legacy_info = ctx.accounts.legacy_bitmap.to_account_info();
+        let mut legacy = read_legacy_bitmap(&legacy_info)?.ok_or(SharelyError::AlreadyMigrated)?;
+        require!(legacy.user_count > 0, SharelyError::AlreadyMigrated);
+        require!(
+            shard_id == bitmap_shard_id(legacy.user_count as u64 - 1),
+            SharelyError::InvalidArgument
+        );
+        let shard_user_count = legacy.user_count - shard_id as u32 * BITS_PER_SHARD;
+        let quest = &ctx.accounts.quest;
+        require!(
+            shard_user_count == quest.shard_user_count(shard_id),
+            SharelyError::AccountMismatch
+        );
+
+        {
+            let mut header = ctx.accounts.bitmap_shard.load_init()?;
+            header.quest = quest.key();
+            header.shard_id = shard_id;
+            header.user_count = shard_user_count;
+            header.payer = ctx.accounts.admin.key();
+        }
+        let start = shard_id as usize * (BITS_PER_SHARD / 8) as usize;
+        {
+            let src = legacy.bits.get(start..).unwrap_or(&[]);
+            let mut data = ctx.accounts.bitmap_shard.as_ref().try_borrow_mut_data()?;
+            let dst = &mut data[ClaimBitmapShard::BITS_OFFSET..];
+            let len = src.len().min(dst.len());
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+
+        // 截断旧位图，退回多余租金；最后一个分片迁移完成后关闭旧账户
+        let admin_info = ctx.accounts.admin.to_account_info();
+        legacy.user_count = shard_id as u32 * BITS_PER_SHARD;
+        legacy.bits.truncate(start);
+        let keep_lamports = if legacy.user_count == 0 {
+            legacy_info.assign(&anchor_lang::system_program::ID);
+            legacy_info.resize(0)?;
+            0
+        } else {
+            let serialized = legacy.try_to_vec()?;
+            let new_len = 8 + serialized.len();
+            legacy_info.try_borrow_mut_data()?[8..new_len].copy_from_slice(&serialized);
+            legacy_info.resize(new_len)?;
+            Rent::get()?.minimum_balance(new_len)
+        };
+        let refund = legacy_info.lamports().saturating_sub(keep_lamports);
+        **legacy_info.try_borrow_mut_lamports()? -= refund;
+        **admin_info.try_borrow_mut_lamports()? += refund;
+
+        emit!(BitmapMigrated {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            shard_id,
+            user_count: shard_user_count,
+        });
+        Ok(())
+    }
+
+    // =========================
+    // Query Instructions
+    // =========================
+
+    pub fn is_claimed(ctx: Context<IsClaimed>, index: u64) -> Result<bool> {
+        // 检查索引是否在有效范围内
+        require!(
+            index < ctx.accounts.quest.user_count as u64,
+            SharelyError::BitmapIndexOutOfRange
+        );
+
+        is_bit_set(&ctx.accounts.bitmap_shard, index % BITS_PER_SHARD as u64)
+    }
+
+    pub fn verify_eligibility(
+        ctx: Context<VerifyEligibility>,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<bool> {
+        let quest = &ctx.accounts.quest;
+
+        require!(quest.status == Status::Active, SharelyError::QuestNotActive);
+
+        let leaf = leaf_hash(
+            quest.leaf_format,
+            &quest.key(),
+            index,
+            ctx.accounts.user.key(),
+            amount,
+        );
+        let computed_root = compute_merkle_root_sorted(quest.leaf_format, leaf, &proof);
+
+        let is_valid = computed_root == quest.merkle_root;
+
+        Ok(is_valid)
+    }
+
+    // 更新 treasury 地址（仅 Treasurer 可调用）
+    pub fn update_treasury(ctx: Context<UpdateTreasury>, new_treasury: Pubkey) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::Treasurer,
+        )?;
+        ctx.accounts.config.treasury = new_treasury;
+        emit!(TreasuryUpdated {
+            new_treasury,
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    // 更新全局手续费策略（仅 Treasurer 可调用），只影响之后创建的 quest
+    pub fn set_fee_policy(ctx: Context<UpdateTreasury>, fee_policy: FeePolicy) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::Treasurer,
+        )?;
+        fee_policy.validate()?;
+        ctx.accounts.config.fee_policy = fee_policy;
+        emit!(FeePolicyUpdated {
+            fee_policy,
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    // 开启或关闭激活时预收手续费（仅 Treasurer 可调用）
+    pub fn set_fee_upfront(ctx: Context<UpdateTreasury>, upfront: bool) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::Treasurer,
+        )?;
+        ctx.accounts.config.fee_upfront = upfront;
+        emit!(FeeUpfrontUpdated {
+            upfront,
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    // 调整单个 quest 的手续费策略（仅 Treasurer 可调用），关闭前均可修改
+    pub fn set_quest_fee_policy(ctx: Context<AdminOnQuest>, fee_policy: FeePolicy) -> Result<()> {
+        require_role(
+            &ctx.accounts.config,
+            &ctx.accounts.admin.key(),
+            Role::Treasurer,
+        )?;
+        let quest = &mut ctx.accounts.quest;
+        require!(
+            matches!(
+                quest.status,
+                Status::Pending | Status::Active | Status::Paused
+            ),
+            SharelyError::InvalidStatus
+        );
+        fee_policy.validate()?;
+        // 已预收的手续费无法退回，新策略保证收取的金额不得低于已收取金额
+        require!(
+            fee_policy.upfront_fee(quest.fee_amount) >= quest.fee_paid,
+            SharelyError::InvalidFeePolicy
+        );
+        quest.fee_policy = fee_policy;
+        emit!(QuestFeePolicyUpdated {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            fee_policy,
+        });
+        Ok(())
+    }
+}
+
+// =========================
+// Accounts
+// =========================
+
+#[account]
+pub struct QuestAccount {
+    pub quest_id: u64,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub vault_authority: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub claimed_total: u64,
+    pub status: Status,
+    pub version: u32,
+    pub merchant: Pubkey,
+    pub admin: Pubkey,
+    pub start_at: i64,
+    pub end_at: i64,
+    pub total_amount: u64,
+    pub funded_amount: u64,
+    pub fee_amount: u64,
+    pub approved_start_at: i64,    // 审批允许的最早开始时间
+    pub approved_end_at: i64,      // 审批允许的最晚结束时间
+    pub approved_fee_amount: u64,  // 审批允许的最高手续费
+    pub asset: QuestAsset,         // 奖励资产类型，旧 quest 为 Token
+    pub user_count: u32,           // 空投总人数，按 BITS_PER_SHARD 切分到各位图分片
+    pub leaf_format: LeafFormat,   // 默克尔叶子格式，旧 quest 为 V1
+    pub total_allocated: u64,      // 激活时承诺的空投总额，claimed_total 不得超过
+    pub claim_mode: ClaimMode,     // 领取方式，旧 quest 为 Bitmap
+    pub cliff_at: i64,             // vesting：此前不可领取
+    pub vesting_start: i64,        // vesting：线性解锁开始时间
+    pub vesting_end: i64,          // vesting：完全解锁时间
+    pub phase_count: u16,          // 追加阶段数量，阶段 id 为 1..=phase_count，旧 quest 为 0
+    pub phases_end_at: i64,        // 追加阶段中最晚的结束时间
+    pub fee_policy: FeePolicy,     // 关闭时的手续费计算方式，旧 quest 为 Flat（收取 fee_amount）
+    pub fee_paid: u64,             // 激活时已预先转入 treasury 的手续费，关闭时不再重复收取
+    pub transfer_fee_reserve: u64, // 为 Token-2022 transfer-fee 预留、尚未用掉的金额，vault 在每笔转出时承担
+    pub transfer_fees_paid: u64,   // vault 已为转出承担的 transfer-fee
+}
+
+impl QuestAccount {
+    // space 计算：
+    // 8 (discriminator)
+    // + 8 (quest_id)
+    // + 32 (mint)
+    // + 32 (vault)
+    // + 32 (vault_authority)
+    // + 32 (merkle_root)
+    // + 8 (claimed_total)
+    // + 1 (status)
+    // + 4 (version)
+    // + 32 (merchant)
+    // + 32 (admin)
+    // + 8 (start_at)
+    // + 8 (end_at)
+    // + 8 (total_amount)
+    // + 8 (funded_amount)
+    // + 8 (fee_amount)
+    // + 8 (approved_start_at)
+    // + 8 (approved_end_at)
+    // + 8 (approved_fee_amount)
+    // + 1 (asset)
+    // + 4 (user_count)
+    // + 1 (leaf_format)
+    // + 8 (total_allocated)
+    // + 1 (claim_mode)
+    // + 8 (cliff_at)
+    // + 8 (vesting_start)
+    // + 8 (vesting_end)
+    // + 2 (phase_count)
+    // + 8 (phases_end_at)
+    // + FeePolicy::SIZE (fee_policy)
+    // + 8 (fee_paid)
+    // + 8 (transfer_fee_reserve)
+    // + 8 (transfer_fees_paid)
+    pub const SPACE: usize = 8
+        + 8
+        + 32
+        + 32
+        + 32
+        + 32
+        + 8
+        + 1
+        + 4
+        + 32
+        + 32
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + 1
+        + 4
+        + 1
+        + 8
+        + 1
+        + 8
+        + 8
+        + 8
+        + 2
+        + 8
+        + FeePolicy::SIZE
+        + 8
+        + 8
+        + 8;
+
+    // 须由已注资金额覆盖的总额：空投总额 + 手续费 + 剩余的 transfer-fee 预留 + 已承担的 transfer-fee
+    pub fn committed_amount(&self) -> Result<u64> {
+        self.total_allocated
+            .checked_add(self.fee_amount)
+            .and_then(|amount| amount.checked_add(self.transfer_fee_reserve))
+            .and_then(|amount| amount.checked_add(self.transfer_fees_paid))
+            .ok_or(error!(SharelyError::Overflow))
+    }
+
+    // 记入 vault 为一笔转出承担的 transfer-fee：先从预留中扣除，超出预留的部分
+    // （如 vesting / 累计分发的多次部分领取）须由尚未承诺的注资覆盖，否则拒绝，避免挤占其他用户的额度
+    pub fn charge_transfer_fee(&mut self, fee: u64) -> Result<()> {
+        self.transfer_fee_reserve = self.transfer_fee_reserve.saturating_sub(fee);
+        self.transfer_fees_paid = self
+            .transfer_fees_paid
+            .checked_add(fee)
+            .ok_or(SharelyError::Overflow)?;
+        require!(
+            self.committed_amount()? <= self.funded_amount,
+            SharelyError::TransferFeeReserveExceeded
+        );
+        Ok(())
+    }
+
+    // vesting quest 在 now 时刻 allocation 中已解锁的部分
+    pub fn vested_amount(&self, allocation: u64, now: i64) -> Result<u64> {
+        if now < self.cliff_at {
+            return Ok(0);
+        }
+        if now >= self.vesting_end {
+            return Ok(allocation);
+        }
+        let elapsed = now.saturating_sub(self.vesting_start).max(0) as u128;
+        let duration = (self.vesting_end - self.vesting_start) as u128;
+        let vested = (allocation as u128)
+            .checked_mul(elapsed)
+            .ok_or(SharelyError::Overflow)?
+            / duration;
+        Ok(vested as u64)
+    }
+
+    // 位图分片数量
+    pub fn shard_count(&self) -> u32 {
+        self.user_count.div_ceil(BITS_PER_SHARD)
+    }
+
+    // 分片 shard_id 覆盖的用户数（最后一个分片可能不满，超出范围为 0）
+    pub fn shard_user_count(&self, shard_id: u16) -> u32 {
+        shard_user_count(self.user_count, shard_id)
+    }
+
+    // 关闭时按手续费策略结算的手续费，不超过激活时预留的 fee_amount，并扣除已预先收取的部分；
+    // 预收金额不超过策略的最低手续费，差额不会为负
+    pub fn settlement_fee(&self) -> Result<u64> {
+        let fee =
+            self.fee_policy
+                .fee_for(self.fee_amount, self.claimed_total, self.funded_amount)?;
+        Ok(fee.min(self.fee_amount).saturating_sub(self.fee_paid))
+    }
+
+    // phase 0 与各追加阶段中最晚的领取截止时间
+    pub fn claim_window_end(&self) -> i64 {
+        self.end_at.max(self.phases_end_at)
+    }
+}
+
+// quest 的追加领取阶段，seeds = ["phase", quest, phase_id]。
+// 位图分片 seeds 为 ["bitmap", quest, shard_id_le || phase_id_le]
+#[account]
+pub struct QuestPhase {
+    pub quest: Pubkey,
+    pub phase_id: u16,
+    pub merkle_root: [u8; 32],
+    pub leaf_format: LeafFormat,
+    pub start_at: i64,
+    pub end_at: i64,
+    pub user_count: u32,
+    pub total_allocated: u64, // 本阶段空投总额，已计入 quest.total_allocated
+    pub claimed_total: u64,
+    pub payer: Pubkey, // 创建阶段并支付租金的 operator，关闭时租金退还此地址
+}
+
+impl QuestPhase {
+    // discriminator + quest + phase_id + merkle_root + leaf_format + start_at + end_at
+    // + user_count + total_allocated + claimed_total + payer
+    pub const SPACE: usize = 8 + 32 + 2 + 32 + 1 + 8 + 8 + 4 + 8 + 8 + 32;
+
+    pub fn shard_count(&self) -> u32 {
+        self.user_count.div_ceil(BITS_PER_SHARD)
+    }
+
+    pub fn shard_user_count(&self, shard_id: u16) -> u32 {
+        shard_user_count(self.user_count, shard_id)
+    }
+}
+
+// 零拷贝位图分片：账户数据为 discriminator + 定长头部，位图字节紧随其后，
+// 领取时只读写 index 所在的字节，不反序列化整块位图
+#[account(zero_copy)]
+pub struct ClaimBitmapShard {
+    pub quest: Pubkey,
+    pub user_count: u32, // 本分片覆盖的用户数量
+    pub shard_id: u16, // 覆盖 index ∈ [shard_id * BITS_PER_SHARD, (shard_id + 1) * BITS_PER_SHARD)
+    pub phase_id: u16, // 所属阶段，旧分片为 0
+    pub payer: Pubkey, // 创建分片并支付租金的 operator，关闭时租金退还此地址
+}
+
+impl ClaimBitmapShard {
+    // 位图在账户数据中的起始偏移
+    pub const BITS_OFFSET: usize = 8 + std::mem::size_of::<ClaimBitmapShard>();
+
+    pub fn space(user_count: u32) -> usize {
+        Self::BITS_OFFSET + user_count.div_ceil(8) as usize
+    }
+}
+
+// quest 关闭或取消后保留的审计记录，seeds = ["tombstone", quest]；同时标记该 quest_id 已使用，不可重新创建
+#[account]
+pub struct QuestTombstone {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub status: Status, // Closed 或 Cancelled
+    pub funded_amount: u64,
+    pub claimed_total: u64,
+    pub fee_amount: u64, // 转入 treasury 的手续费总额（含激活时预收的部分）
+    pub remaining_transferred: u64, // 退回商户的剩余金额
+    pub closed_at: i64,
+}
+
+impl QuestTombstone {
+    // discriminator + quest + quest_id + merchant + mint + status + funded_amount
+    // + claimed_total + fee_amount + remaining_transferred + closed_at
+    pub const SPACE: usize = 8 + 32 + 8 + 32 + 32 + 1 + 8 + 8 + 8 + 8 + 8;
+}
+
+// vesting / 累计分发 quest 的用户领取记录，seeds = ["claim", quest, user]
+#[account]
+pub struct ClaimRecord {
+    pub quest: Pubkey,
+    pub user: Pubkey,
+    pub index: u64, // 叶子 index：vesting 下同一用户只能对应一个叶子，累计分发下为最近一次领取的 index
+    pub claimed_amount: u64, // 已领取金额
+}
+
+impl ClaimRecord {
+    // discriminator + quest + user + index + claimed_amount
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 8;
+}
+
+// 旧版 Borsh 位图布局（seeds = ["bitmap", quest]），仅用于 migrate_bitmap
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyClaimBitmapShard {
+    pub quest: Pubkey,
+    pub shard_id: u16,
+    pub user_count: u32,
+    pub bits: Vec<u8>,
+}
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub pending_admin: Pubkey, // 待接受的新 admin，Pubkey::default() 表示无
+    pub approval_threshold: u8, // 商户创建 quest 所需的审批签名数
+    pub funding_approval_required: bool, // fund_quest 是否需要审批签名
+    pub fee_policy: FeePolicy, // 新建 quest 默认的手续费策略
+    pub fee_upfront: bool,     // activate_quest 时是否预先将 fee_amount 转入 treasury
+    pub close_grace_period: i64, // 领取窗口结束后，超过该秒数 admin 可代商户关闭 quest
+    pub roles: Vec<RoleGrant>, // 角色授权列表，admin 为超级管理员，隐式拥有全部角色
+}
+
+impl Config {
+    // discriminator + admin + treasury + pending_admin + approval_threshold
+    // + funding_approval_required + fee_policy + fee_upfront + close_grace_period
+    // + roles(vec 前缀 + 每项 32 + 1)
+    pub fn space(role_count: usize) -> usize {
+        8 + 32 + 32 + 32 + 1 + 1 + FeePolicy::SIZE + 1 + 8 + 4 + role_count * (32 + 1)
+    }
+
+    // 不同审批人数量（admin 隐式计入）
+    pub fn approver_count(&self) -> usize {
+        1 + self
+            .roles
+            .iter()
+            .filter(|g| g.role == Role::ApprovalSigner && g.member != self.admin)
+            .count()
+    }
+
+    pub fn has_role(&self, key: &Pubkey, role: Role) -> bool {
+        *key == self.admin
+            || self
+                .roles
+                .iter()
+                .any(|g| g.member == *key && g.role == role)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RoleGrant {
+    pub member: Pubkey,
+    pub role: Role,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    ApprovalSigner, // 签发商户创建 quest 的离线审批
+    QuestOperator,  // activate / pause / resume / cancel quest
+    Treasurer,      // update_treasury
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pending,
+    Active,
+    Paused,
+    Closed,
+    Cancelled,
+}
+
+impl Status {
+    // 状态转换表：
+    // Pending → Active（activate_quest）/ Cancelled（cancel_quest）
+    // Active  → Paused（pause_quest）/ Closed（close_quest_by_merchant / close_quest_by_admin）
+    //         / Cancelled（未发生领取时 cancel_quest，否则 emergency_cancel_quest）
+    // Paused  → Active（resume_quest / activate_quest）/ Closed（close_quest_by_merchant / close_quest_by_admin）
+    //         / Cancelled（同 Active）
+    // Closed、Cancelled 为终态
+    pub fn can_transition_to(self, next: Status) -> bool {
+        matches!(
+            (self, next),
+            (Status::Pending, Status::Active)
+                | (Status::Pending, Status::Cancelled)
+                | (Status::Active, Status::Paused)
+                | (Status::Active, Status::Closed)
+                | (Status::Active, Status::Cancelled)
+                | (Status::Paused, Status::Active)
+                | (Status::Paused, Status::Closed)
+                | (Status::Paused, Status::Cancelled)
+        )
+    }
+
+    // 仅从 Active / Paused 结束的 quest 收取手续费，未运行过的 quest 不收费
+    pub fn charges_fee(self) -> bool {
+        matches!(self, Status::Active | Status::Paused)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeeKind {
+    #[default]
+    Flat, // 收取激活时设置的 fee_amount
+    ClaimedBps, // claimed_total 的 bps
+    FundedBps,  // funded_amount 的 bps
+}
+
+// 手续费策略：按 kind 计算后限制在 [min_fee, max_fee] 内（max_fee 为 0 表示不设上限），
+// 最终不超过激活时预留的 fee_amount
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FeePolicy {
+    pub kind: FeeKind,
+    pub bps: u16,
+    pub min_fee: u64,
+    pub max_fee: u64,
+}
+
+impl FeePolicy {
+    // kind + bps + min_fee + max_fee
+    pub const SIZE: usize = 1 + 2 + 8 + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.bps as u64 <= BPS_DENOMINATOR,
+            SharelyError::InvalidFeePolicy
+        );
+        require!(
+            self.max_fee == 0 || self.min_fee <= self.max_fee,
+            SharelyError::InvalidFeePolicy
+        );
+        Ok(())
+    }
+
+    // 激活时即可确定的最低手续费：Flat 为 fee_amount，bps 策略为 min_fee（不超过 fee_amount）
+    pub fn upfront_fee(&self, flat: u64) -> u64 {
+        match self.kind {
+            FeeKind::Flat => flat,
+            FeeKind::ClaimedBps | FeeKind::FundedBps => self.min_fee.min(flat),
+        }
+    }
+
+    pub fn fee_for(&self, flat: u64, claimed_total: u64, funded_amount: u64) -> Result<u64> {
+        let base = match self.kind {
+            FeeKind::Flat => return Ok(flat),
+            FeeKind::ClaimedBps => claimed_total,
+            FeeKind::FundedBps => funded_amount,
+        };
+        let fee = (base as u128)
+            .checked_mul(self.bps as u128)
+            .ok_or(SharelyError::Overflow)?
+            / BPS_DENOMINATOR as u128;
+        let fee = u64::try_from(fee).map_err(|_| SharelyError::Overflow)?;
+        let fee = fee.max(self.min_fee);
+        Ok(if self.max_fee > 0 {
+            fee.min(self.max_fee)
+        } else {
+            fee
+        })
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum QuestAsset {
+    Token,     // SPL Token / Token-2022，存放在 vault ATA
+    NativeSol, // 原生 SOL，lamports 存放在 vault_authority PDA
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimMode {
+    Bitmap,     // 一次性领取全部 amount，位图标记
+    Vesting,    // 按 cliff/线性解锁多次领取，领取记录累计
+    Cumulative, // 叶子为累计应得总额，root 可随时轮换，领取记录累计
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LeafFormat {
+    V1, // sha256(index_le || user || amount_le)，节点 sha256(min || max)
+    V2, // sha256(0x00 || quest || index_le || user || amount_le)，节点 sha256(0x01 || min || max)
+}
+
+// 商户创建 quest 的离线审批消息（v2），审批人对其 borsh 序列化结果做 ed25519 签名
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct QuestApproval {
+    pub domain: [u8; 32], // sha256("sharely:v2")
+    pub program_id: Pubkey,
+    pub admin: Pubkey,
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub quest_id: u64,
+    pub total_amount: u64,
+    pub start_at: i64,
+    pub end_at: i64,
+    pub fee_amount: u64,
+    pub expires_at: i64, // 审批过期时间，过期后不可再用于创建 quest
+}
+
+// 追加注资的离线审批消息，审批人对其 borsh 序列化结果做 ed25519 签名
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FundingApproval {
+    pub domain: [u8; 32], // sha256("sharely:fund:v1")
+    pub program_id: Pubkey,
+    pub quest: Pubkey,
+    pub merchant: Pubkey,
+    pub amount: u64,
+    pub funded_amount: u64, // 注资前的 funded_amount，保证每份审批只能使用一次
+    pub expires_at: i64,
+}
+
+// claim_many 的单个条目，账户通过 remaining_accounts 传入
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimEntry {
+    pub quest: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+// 代付领取的用户授权消息，叶子中的用户对其 borsh 序列化结果做 ed25519 签名。
+// 同一 index 只能领取一次，由位图防止重放
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimAuthorization {
+    pub domain: [u8; 32], // sha256("sharely:claim:v1")
+    pub program_id: Pubkey,
+    pub quest: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub expires_at: i64,
+}
+
+// =========================
+// Contexts
+// =========================
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: 全局配置账户，使用固定种子
+    #[account(
+        init,
+        payer = admin,
+        space = Config::space(0),
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChangeAdmin<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin @ SharelyError::Unauthorized, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct GrantRole<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ SharelyError::Unauthorized,
+        seeds = [b"config"],
+        bump,
+        realloc = Config::space(config.roles.len() + 1),
+        realloc::payer = admin,
+        realloc::zero = false
+    )]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeRole<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ SharelyError::Unauthorized,
+        seeds = [b"config"],
+        bump,
+        realloc = Config::space(config.roles.len().saturating_sub(1)),
+        realloc::payer = admin,
+        realloc::zero = false
+    )]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(quest_id: u64)]
+pub struct InitializeQuestByMerchant<'info> {
+    // 移除 admin 账户，因为使用固定公钥验证
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+    // 原生 SOL quest 不传 token 相关账户
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub merchant_source_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(init, payer = merchant, space = QuestAccount::SPACE, seeds = [b"quest".as_ref(), &quest_id.to_le_bytes()], bump)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: quest 关闭或取消时创建的审计记录；存在即说明该 quest_id 已使用过，
+    /// 审批不含 nonce，需以此阻止同一审批重新创建 quest
+    #[account(
+        seeds = [b"tombstone", quest.key().as_ref()],
+        bump,
+        constraint = tombstone.data_is_empty() @ SharelyError::QuestIdUsed
+    )]
+    pub tombstone: UncheckedAccount<'info>,
+    #[account(mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: PDA authority derived by program，原生 SOL quest 的 lamports 也存放于此
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    /// CHECK: ATA will be created manually
+    #[account(mut)]
+    pub vault: Option<UncheckedAccount<'info>>,
+    /// CHECK: instructions sysvar for ed25519 verification
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::id())]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct FundQuest<'info> {
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+    #[account(mut, has_one = merchant @ SharelyError::Unauthorized)]
+    pub quest: Account<'info, QuestAccount>,
+    #[account(
+        mut,
+        constraint = merchant_source_ata.mint == quest.mint @ SharelyError::AccountMismatch
+    )]
+    pub merchant_source_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = quest.mint @ SharelyError::AccountMismatch, mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: PDA authority，原生 SOL quest 的 lamports 存放于此
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: instructions sysvar for ed25519 verification
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::id())]
+    pub instructions: UncheckedAccount<'info>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
-pub struct ChangeAdmin<'info> {
+pub struct AdminOnQuest<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct PublishRoot<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    // Token quest 必传，用于计算 transfer-fee 预留
+    #[account(address = quest.mint @ SharelyError::AccountMismatch)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateQuestWindow<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    // 商户联署，延长到审批时间窗之外时必传
+    #[account(address = quest.merchant @ SharelyError::Unauthorized)]
+    pub merchant: Option<Signer<'info>>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct ActivateQuest<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: 动态大小的位图，shard 0
+    #[account(init_if_needed, payer = admin, space = ClaimBitmapShard::space(0), seeds = [b"bitmap", quest.key().as_ref(), &0u16.to_le_bytes()], bump)]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    // Token quest 必传，用于计算 transfer-fee 预留
+    #[account(address = quest.mint @ SharelyError::AccountMismatch)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    // 以下账户仅在 config.fee_upfront 开启时用于预收手续费
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: Option<UncheckedAccount<'info>>,
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = treasury_ata.mint == quest.mint @ SharelyError::AccountMismatch,
+        constraint = treasury_ata.owner == config.treasury @ SharelyError::Unauthorized
+    )]
+    pub treasury_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: 原生 SOL quest 的手续费直接转入 treasury 地址
+    #[account(mut, address = config.treasury @ SharelyError::Unauthorized)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(phase_id: u16)]
+pub struct AddQuestPhase<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    #[account(init, payer = admin, space = QuestPhase::SPACE, seeds = [b"phase", quest.key().as_ref(), &phase_id.to_le_bytes()], bump)]
+    pub phase: Account<'info, QuestPhase>,
+    /// 阶段位图 shard 0
+    #[account(init, payer = admin, space = ClaimBitmapShard::space(0), seeds = [b"bitmap", quest.key().as_ref(), &bitmap_shard_seed(0, phase_id)], bump)]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    // Token quest 必传，用于计算 transfer-fee 预留
+    #[account(address = quest.mint @ SharelyError::AccountMismatch)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(shard_id: u16, phase_id: u16)]
+pub struct InitBitmapShard<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub quest: Account<'info, QuestAccount>,
+    /// phase_id 为 0 时不传
+    #[account(seeds = [b"phase", quest.key().as_ref(), &phase_id.to_le_bytes()], bump)]
+    pub phase: Option<Account<'info, QuestPhase>>,
+    #[account(init_if_needed, payer = admin, space = ClaimBitmapShard::space(0), seeds = [b"bitmap", quest.key().as_ref(), &bitmap_shard_seed(shard_id, phase_id)], bump)]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(phase_id: u16, index: u64)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: 可选的收款钱包（如冷钱包、交易所充值地址），由 user 签名授权；不传时放款给 user
+    #[account(mut)]
+    pub recipient: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    /// 领取阶段，phase_id 为 0 时不传
+    #[account(mut, seeds = [b"phase", quest.key().as_ref(), &phase_id.to_le_bytes()], bump)]
+    pub phase: Option<Account<'info, QuestPhase>>,
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    // 原生 SOL quest 不传 token 相关账户，lamports 直接转给收款钱包
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: 收款钱包（recipient 或 user）的 ATA，不存在时由 user 创建
+    #[account(mut)]
+    pub user_ata: Option<UncheckedAccount<'info>>,
+    /// CHECK: Mint account (needed for ATA creation)
+    #[account(mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// 动态位图，按 phase_id 与 index 路由到对应分片
+    #[account(mut, seeds = [b"bitmap", quest.key().as_ref(), &bitmap_shard_seed(bitmap_shard_id(index), phase_id)], bump)]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithRecord<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    // 原生 SOL quest 不传 token 相关账户，lamports 直接转给 user
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: user 的 ATA，不存在时由 user 创建
+    #[account(mut)]
+    pub user_ata: Option<UncheckedAccount<'info>>,
+    #[account(mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(init_if_needed, payer = user, space = ClaimRecord::SPACE, seeds = [b"claim", quest.key().as_ref(), user.key().as_ref()], bump)]
+    pub claim_record: Account<'info, ClaimRecord>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMany<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK: 可选的收款钱包，由 user 签名授权，对所有条目生效；不传时放款给 user
+    #[account(mut)]
+    pub recipient: Option<UncheckedAccount<'info>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct ClaimFor<'info> {
+    // 代付交易费与 ATA 租金，无需任何角色
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    /// CHECK: 默克尔叶子中的用户，通过 ed25519 签名授权本次领取
+    pub user: UncheckedAccount<'info>,
+    /// CHECK: 收款钱包，须与授权消息中的 recipient 一致
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    // 原生 SOL quest 不传 token 相关账户，lamports 直接转给 recipient
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: recipient 的 ATA，不存在时由 relayer 创建
+    #[account(mut)]
+    pub recipient_ata: Option<UncheckedAccount<'info>>,
+    #[account(mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, seeds = [b"bitmap", quest.key().as_ref(), &bitmap_shard_id(index).to_le_bytes()], bump)]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: instructions sysvar for ed25519 verification
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::id())]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u64)]
+pub struct IsClaimed<'info> {
+    pub quest: Account<'info, QuestAccount>,
+    #[account(seeds = [b"bitmap", quest.key().as_ref(), &bitmap_shard_id(index).to_le_bytes()], bump)]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    /// CHECK: user
+    pub user: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyEligibility<'info> {
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: 仅用于身份标识，不读取数据
+    pub user: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseQuestByMerchant<'info> {
+    #[account(mut)]
+    pub merchant: Signer<'info>,
+    #[account(mut, has_one = merchant, close = merchant)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    // 原生 SOL quest 不传 token 相关账户，剩余 lamports 退回 merchant
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    // 关闭 vault 前需将 Token-2022 预扣的 transfer-fee 归集到 mint
+    #[account(mut, address = quest.mint @ SharelyError::AccountMismatch, mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub destination_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: 验证 treasury_ata 是否为授权 treasury 地址的 ATA
+    #[account(
+        mut,
+        constraint = treasury_ata.mint == quest.mint @ SharelyError::AccountMismatch,
+        constraint = treasury_ata.owner == config.treasury @ SharelyError::Unauthorized
+    )]
+    pub treasury_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: 原生 SOL quest 的手续费直接转入 treasury 地址
+    #[account(mut, address = config.treasury @ SharelyError::Unauthorized)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+    // 审计记录，同时阻止 quest PDA 被重新创建，租金由 merchant 支付
+    #[account(init, payer = merchant, space = QuestTombstone::SPACE, seeds = [b"tombstone", quest.key().as_ref()], bump)]
+    pub tombstone: Account<'info, QuestTombstone>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseQuestByAdmin<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, close = merchant)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    // 原生 SOL quest 不传 token 相关账户，剩余 lamports 退回 merchant
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    // 关闭 vault 前需将 Token-2022 预扣的 transfer-fee 归集到 mint
+    #[account(mut, address = quest.mint @ SharelyError::AccountMismatch, mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: 商户的 ATA，在指令中校验地址，不存在时由 admin 创建
+    #[account(mut)]
+    pub merchant_ata: Option<UncheckedAccount<'info>>,
+    /// CHECK: 原生 SOL quest 的退款地址，同时接收 quest 与 vault ATA 的租金
+    #[account(mut, address = quest.merchant @ SharelyError::AccountMismatch)]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        constraint = treasury_ata.mint == quest.mint @ SharelyError::AccountMismatch,
+        constraint = treasury_ata.owner == config.treasury @ SharelyError::Unauthorized
+    )]
+    pub treasury_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: 原生 SOL quest 的手续费直接转入 treasury 地址
+    #[account(mut, address = config.treasury @ SharelyError::Unauthorized)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+    // 审计记录，同时阻止 quest PDA 被重新创建，租金由 admin 支付
+    #[account(init, payer = admin, space = QuestTombstone::SPACE, seeds = [b"tombstone", quest.key().as_ref()], bump)]
+    pub tombstone: Account<'info, QuestTombstone>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelQuest<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, close = merchant)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    // 原生 SOL quest 不传 token 相关账户，lamports 退回 merchant
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = quest.mint @ SharelyError::AccountMismatch, mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Merchant ATA，验证地址是否正确
+    #[account(
+        mut,
+        constraint = merchant_ata.mint == quest.mint @ SharelyError::AccountMismatch,
+        constraint = merchant_ata.owner == quest.merchant @ SharelyError::AccountMismatch
+    )]
+    pub merchant_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: 原生 SOL quest 的退款地址，同时接收 quest 与 vault ATA 的租金
+    #[account(mut, address = quest.merchant @ SharelyError::AccountMismatch)]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    // 审计记录，同时阻止 quest PDA 被重新创建，租金由 admin 支付
+    #[account(init, payer = admin, space = QuestTombstone::SPACE, seeds = [b"tombstone", quest.key().as_ref()], bump)]
+    pub tombstone: Account<'info, QuestTombstone>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyCancelQuest<'info> {
+    pub admin: Signer<'info>,
+    // quest 不关闭，保留 Cancelled 状态以防同一 quest_id 被重新初始化
+    #[account(mut)]
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: PDA authority
+    #[account(mut, seeds = [b"vault_auth", quest.key().as_ref()], bump)]
+    pub vault_authority: UncheckedAccount<'info>,
+    // 原生 SOL quest 不传 token 相关账户，lamports 退回 merchant
+    #[account(mut, address = quest.vault)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, address = quest.mint @ SharelyError::AccountMismatch, mint::token_program = token_program)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        constraint = merchant_ata.mint == quest.mint @ SharelyError::AccountMismatch,
+        constraint = merchant_ata.owner == quest.merchant @ SharelyError::AccountMismatch
+    )]
+    pub merchant_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: 原生 SOL quest 的退款地址，同时接收 vault ATA 的租金
+    #[account(mut, address = quest.merchant @ SharelyError::AccountMismatch)]
+    pub merchant: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBitmapShard<'info> {
+    /// CHECK: 已关闭或取消的 quest 地址，仅用于推导 tombstone 与核对分片归属
+    pub quest: UncheckedAccount<'info>,
+    // 只有关闭或取消后才存在；emergency_cancel_quest 保留的 quest 没有 tombstone，其分片不可关闭
+    #[account(seeds = [b"tombstone", quest.key().as_ref()], bump)]
+    pub tombstone: Account<'info, QuestTombstone>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = quest @ SharelyError::AccountMismatch,
+        has_one = payer @ SharelyError::AccountMismatch
+    )]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    /// CHECK: 创建分片时的付费方，由 has_one 校验
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseQuestPhase<'info> {
+    /// CHECK: 已关闭或取消的 quest 地址，仅用于推导 tombstone 与核对阶段归属
+    pub quest: UncheckedAccount<'info>,
+    #[account(seeds = [b"tombstone", quest.key().as_ref()], bump)]
+    pub tombstone: Account<'info, QuestTombstone>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = quest @ SharelyError::AccountMismatch,
+        has_one = payer @ SharelyError::AccountMismatch
+    )]
+    pub phase: Account<'info, QuestPhase>,
+    /// CHECK: 创建阶段时的付费方，由 has_one 校验
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: 旧布局的 config 无法按当前结构反序列化，由指令手动校验 discriminator 与 admin
+    #[account(mut, owner = crate::ID, seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateQuest<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: 旧布局的 quest 无法按当前结构反序列化，由指令手动校验 discriminator
+    #[account(mut, owner = crate::ID)]
+    pub quest: UncheckedAccount<'info>,
+    /// CHECK: 旧版 Borsh 位图，可能不存在
+    #[account(seeds = [b"bitmap", quest.key().as_ref()], bump)]
+    pub legacy_bitmap: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(shard_id: u16)]
+pub struct MigrateBitmap<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub quest: Account<'info, QuestAccount>,
+    /// CHECK: 旧版 Borsh 位图，由指令手动解析
+    #[account(mut, owner = crate::ID, seeds = [b"bitmap", quest.key().as_ref()], bump)]
+    pub legacy_bitmap: UncheckedAccount<'info>,
+    #[account(init, payer = admin, space = ClaimBitmapShard::space(quest.shard_user_count(shard_id)), seeds = [b"bitmap", quest.key().as_ref(), &shard_id.to_le_bytes()], bump)]
+    pub bitmap_shard: AccountLoader<'info, ClaimBitmapShard>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateTreasury<'info> {
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub admin: Signer<'info>,
+    /// CHECK: 全局配置账户
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// =========================
+// Events
+// =========================
+
+#[event]
+pub struct Initialized {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub current_admin: Pubkey,
+    pub proposed_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminProposalCancelled {
+    pub admin: Pubkey,
+    pub cancelled_admin: Pubkey,
+}
+
+#[event]
+pub struct RoleGranted {
+    pub admin: Pubkey,
+    pub member: Pubkey,
+    pub role: Role,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub admin: Pubkey,
+    pub member: Pubkey,
+    pub role: Role,
+}
+
+#[event]
+pub struct ApprovalThresholdUpdated {
+    pub admin: Pubkey,
+    pub threshold: u8,
+    pub approver_count: u8,
+}
+
+#[event]
+pub struct FundingApprovalRequirementUpdated {
+    pub admin: Pubkey,
+    pub required: bool,
+}
+
+#[event]
+pub struct CloseGracePeriodUpdated {
+    pub admin: Pubkey,
+    pub grace_period: i64,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct QuestCreated {
+    pub status: Status,
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub merchant: Pubkey,
+    pub mint: Pubkey,
+    pub total_amount: u64,
+    pub approved_start_at: i64,
+    pub approved_end_at: i64,
+    pub approved_fee_amount: u64,
+    pub approval_expires_at: i64,
+    pub asset: QuestAsset,
+}
+
+#[event]
+pub struct VestingConfigured {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub cliff_at: i64,
+    pub vesting_start: i64,
+    pub vesting_end: i64,
+}
+
+#[event]
+pub struct CumulativeConfigured {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+}
+
+#[event]
+pub struct RootPublished {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub version: u32,
+    pub merkle_root: [u8; 32],
+    pub user_count: u32,
+    pub total_allocated: u64,
+}
+
+#[event]
+pub struct QuestPhaseAdded {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub phase_id: u16,
+    pub merkle_root: [u8; 32],
+    pub start_at: i64,
+    pub end_at: i64,
+    pub user_count: u32,
+    pub shard_count: u32, // 需创建的位图分片数量
+    pub leaf_format: LeafFormat,
+    pub total_allocated: u64,
+}
+
+#[event]
+pub struct QuestWindowUpdated {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub start_at: i64,
+    pub old_end_at: i64,
+    pub end_at: i64,
+    pub merchant_signed: bool, // 是否有商户联署
+}
+
+#[event]
+pub struct QuestStatusChanged {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub status: Status,
+}
+
+#[event]
+pub struct VaultFunded {
+    pub funder: Pubkey,
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub amount: u64,
+    pub funded_amount: u64, // 注资后的累计注资额
+}
+
+#[event]
+pub struct QuestActivated {
+    pub status: Status,
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub version: u32,
+    pub merkle_root: [u8; 32],
+    pub start_at: i64,
+    pub end_at: i64,
+    pub fee_amount: u64,
+    pub fee_paid: u64, // 已预先转入 treasury 的手续费
+    pub user_count: u32,
+    pub shard_count: u32, // 需创建的位图分片数量
+    pub leaf_format: LeafFormat,
+    pub total_allocated: u64,
+}
+
+#[event]
+pub struct Claimed {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub user: Pubkey,
+    pub index: u64,
+    pub amount: u64,
+    pub version: u32,
+    pub transfer_fee: u64, // Token-2022 transfer-fee，由 vault 额外承担
+    pub recipient: Pubkey, // 实际收款钱包，普通 claim 与 user 相同
+    pub phase_id: u16,
+}
+
+#[event]
+pub struct QuestClosed {
+    pub status: Status,
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub remaining_transferred: u64,
+    pub recipient: Pubkey,
+}
+
+#[event]
+pub struct QuestClosedByAdmin {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub admin: Pubkey,
+    pub close_after: i64, // 领取窗口结束时间 + 宽限期
+}
+
+#[event]
+pub struct QuestCancelled {
+    pub status: Status,
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub remaining_transferred: u64, // 退回商户的未领取余额
+    pub recipient: Pubkey,
+    pub previous_status: Status,
+    pub funded_amount: u64,
+    pub claimed_total: u64,
+    pub fee_paid: u64,   // 激活时已预收、不予退回的手续费
+    pub emergency: bool, // 由 emergency_cancel_quest 取消，quest 与位图账户保留
+}
+
+#[event]
+pub struct BitmapShardClosed {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub shard_id: u16,
+    pub phase_id: u16,
+    pub payer: Pubkey,
+    pub rent: u64, // 退还给 payer 的租金
+}
+
+#[event]
+pub struct QuestPhaseClosed {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub phase_id: u16,
+    pub payer: Pubkey,
+    pub rent: u64, // 退还给 payer 的租金
+}
+
+#[event]
+pub struct QuestRentReclaimed {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub vault_rent: u64,  // vault ATA 租金，退还商户
+    pub bitmap_rent: u64, // 位图分片与阶段账户租金，退还各自的付费方
+    pub quest_rent: u64,  // quest 账户租金，退还商户
+}
+
+#[event]
+pub struct BitmapInitialized {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub user_count: u32,
+    pub bitmap_size: u32,
+    pub shard_id: u16,
+    pub phase_id: u16,
+}
+
+#[event]
+pub struct ConfigMigrated {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct QuestMigrated {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub user_count: u32,
+}
+
+#[event]
+pub struct BitmapMigrated {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub shard_id: u16,
+    pub user_count: u32,
+}
+
+#[event]
+pub struct TreasuryUpdated {
+    pub new_treasury: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct FeeTransferred {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub fee_amount: u64,
+    pub recipient: Pubkey,
+    pub fee_policy: FeePolicy, // 计算 fee_amount 所用的策略
+}
+
+#[event]
+pub struct FeeUpfrontUpdated {
+    pub upfront: bool,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct FeePolicyUpdated {
+    pub fee_policy: FeePolicy,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct QuestFeePolicyUpdated {
+    pub quest: Pubkey,
+    pub quest_id: u64,
+    pub fee_policy: FeePolicy,
+}
+
+// =========================
+// Errors
+// =========================
+#[error_code]
+pub enum SharelyError {
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid status")]
+    InvalidStatus,
+    #[msg("Invalid mint or account mismatch")]
+    AccountMismatch,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Invalid proof")]
+    InvalidProof,
+    #[msg("Already claimed")]
+    AlreadyClaimed,
+    #[msg("Vault insufficient")]
+    VaultInsufficient,
+    #[msg("Proof too long")]
+    ProofTooLong,
+    #[msg("Bitmap index out of range")]
+    BitmapIndexOutOfRange,
+    #[msg("Quest not active")]
+    QuestNotActive,
+    #[msg("Overflow")]
+    Overflow,
+    #[msg("Invalid argument")]
+    InvalidArgument,
+    #[msg("Invalid ed25519 signature")]
+    InvalidSignature,
+    #[msg("Invalid fee amount")]
+    InvalidFeeAmount,
+    #[msg("No pending admin")]
+    NoPendingAdmin,
+    #[msg("Role already granted")]
+    RoleAlreadyGranted,
+    #[msg("Role not found")]
+    RoleNotFound,
+    #[msg("Too many role grants")]
+    TooManyRoles,
+    #[msg("Invalid approval threshold")]
+    InvalidApprovalThreshold,
+    #[msg("Not enough approval signatures")]
+    InsufficientApprovals,
+    #[msg("Unsupported approval version")]
+    UnsupportedApprovalVersion,
+    #[msg("Approval expired")]
+    ApprovalExpired,
+    #[msg("Quest window outside approved range")]
+    OutsideApprovedWindow,
+    #[msg("Malformed ed25519 instruction")]
+    InvalidEd25519Instruction,
+    #[msg("Accounts do not match quest asset")]
+    AssetAccountMismatch,
+    #[msg("Account already migrated")]
+    AlreadyMigrated,
+    #[msg("Claims exceed total allocation")]
+    AllocationExceeded,
+    #[msg("Instruction does not match quest claim mode")]
+    ClaimModeMismatch,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Invalid status transition")]
+    InvalidStatusTransition,
+    #[msg("Invalid fee policy")]
+    InvalidFeePolicy,
+    #[msg("Close grace period has not elapsed")]
+    GracePeriodNotElapsed,
+    #[msg("Quest has claims, use emergency cancel")]
+    QuestHasClaims,
+    #[msg("Quest id already used")]
+    QuestIdUsed,
+    #[msg("Transfer fee exceeds the reserve, fund the quest")]
+    TransferFeeReserveExceeded,
+}
+
+// =========================
+// Helpers
+// =========================
+
+// V2 叶子绑定 quest 地址，并以前缀区分叶子与内部节点，同一份名单无法跨 quest 复用
+fn leaf_hash(
+    format: LeafFormat,
+    quest: &Pubkey,
+    index: u64,
+    user: Pubkey,
+    amount: u64,
+) -> [u8; 32] {
+    let idx_le = index.to_le_bytes();
+    let amt_le = amount.to_le_bytes();
+    let data: Vec<&[u8]> = match format {
+        LeafFormat::V1 => vec![&idx_le, user.as_ref(), &amt_le],
+        LeafFormat::V2 => vec![
+            MERKLE_LEAF_PREFIX,
+            quest.as_ref(),
+            &idx_le,
+            user.as_ref(),
+            &amt_le,
+        ],
+    };
+    let h = hashv(&data);
+    h.to_bytes()
+}
+
+fn hash_pair_sorted(format: LeafFormat, a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    match format {
+        LeafFormat::V1 => hashv(&[&lo, &hi]).to_bytes(),
+        LeafFormat::V2 => hashv(&[MERKLE_NODE_PREFIX, &lo, &hi]).to_bytes(),
+    }
+}
+
+fn compute_merkle_root_sorted(format: LeafFormat, leaf: [u8; 32], proof: &[[u8; 32]]) -> [u8; 32] {
+    let mut acc = leaf;
+    for p in proof.iter() {
+        acc = hash_pair_sorted(format, acc, *p);
+    }
+    acc
+}
 
-    #[account(mut, seeds = [b"config"], bump)]
-    pub config: Account<'info, Config>,
+// claim_vested / claim_cumulative 共用：以用户领取记录记账
+fn claim_with_record(
+    ctx: Context<ClaimWithRecord>,
+    mode: ClaimMode,
+    index: u64,
+    amount: u64,
+    proof: &[[u8; 32]],
+) -> Result<()> {
+    let user = ctx.accounts.user.to_account_info();
+    process_claim(
+        ClaimPayout {
+            quest: &mut ctx.accounts.quest,
+            phase: None,
+            vault_authority: &ctx.accounts.vault_authority,
+            vault_authority_bump: ctx.bumps.vault_authority,
+            vault: &ctx.accounts.vault,
+            mint: &ctx.accounts.mint,
+            recipient_ata: &ctx.accounts.user_ata,
+            ledger: ClaimLedger::Record(&mut ctx.accounts.claim_record, mode),
+            token_program: &ctx.accounts.token_program,
+            associated_token_program: &ctx.accounts.associated_token_program,
+            system_program: &ctx.accounts.system_program,
+            payer: user.clone(),
+            recipient: user.clone(),
+        },
+        user.key(),
+        index,
+        amount,
+        proof,
+    )
 }
 
-#[derive(Accounts)]
-#[instruction(quest_id: u64)]
-pub struct InitializeQuestByMerchant<'info> {
-    // 移除 admin 账户，因为使用固定公钥验证
-    #[account(mut)]
-    pub merchant: Signer<'info>,
-    #[account(mut)]
-    pub merchant_source_ata: Account<'info, TokenAccount>,
-    // space 计算：
-    // 8 (discriminator)
-    // + 8 (quest_id)
-    // + 32 (mint)
-    // + 32 (vault)
-    // + 32 (vault_authority)
-    // + 32 (merkle_root)
-    // + 8 (claimed_total)
-    // + 1 (status)
-    // + 4 (version)
-    // + 32 (merchant)
-    // + 32 (admin)
-    // + 8 (start_at)
-    // + 8 (end_at)
-    // + 8 (total_amount)
-    // + 8 (funded_amount)
-    #[account(init, payer = merchant, space = 8 + 8 + 32 + 32 + 32 + 32 + 8 + 1 + 4 + 32 + 32 + 8 + 8 + 8 + 8 + 8, seeds = [b"quest".as_ref(), &quest_id.to_le_bytes()], bump)]
-    pub quest: Account<'info, QuestAccount>,
-    pub mint: Account<'info, Mint>,
-    /// CHECK: PDA authority derived by program
-    #[account(seeds = [b"vault_auth", quest.key().as_ref()], bump)]
-    pub vault_authority: UncheckedAccount<'info>,
-    /// CHECK: ATA will be created manually
-    #[account(mut)]
-    pub vault: UncheckedAccount<'info>,
-    /// CHECK: instructions sysvar for ed25519 verification
-    #[account(address = anchor_lang::solana_program::sysvar::instructions::id())]
-    pub instructions: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
-    #[account(mut, seeds = [b"config"], bump)]
-    pub config: Account<'info, Config>,
+// claim / claim_for 共用的账户：recipient 为收款钱包，payer 承担 ATA 租金
+struct ClaimPayout<'a, 'info> {
+    quest: &'a mut Account<'info, QuestAccount>,
+    phase: Option<&'a mut Account<'info, QuestPhase>>, // None 为 phase 0
+
+    vault_authority: &'a UncheckedAccount<'info>,
+    vault_authority_bump: u8,
+    vault: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    mint: &'a Option<InterfaceAccount<'info, Mint>>,
+    recipient_ata: &'a Option<UncheckedAccount<'info>>,
+    ledger: ClaimLedger<'a, 'info>,
+    token_program: &'a Option<Interface<'info, TokenInterface>>,
+    associated_token_program: &'a Option<Program<'info, AssociatedToken>>,
+    system_program: &'a Program<'info, System>,
+    payer: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
 }
 
-#[derive(Accounts)]
-pub struct AdminOnQuest<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(mut)]
-    pub quest: Account<'info, QuestAccount>,
-    #[account(mut, seeds = [b"config"], bump)]
-    pub config: Account<'info, Config>,
+// 领取记账方式：一次性领取在位图中置位，vesting / 累计分发 quest 在领取记录中累计已领金额，
+// Record 携带指令对应的领取方式
+enum ClaimLedger<'a, 'info> {
+    Bitmap(&'a AccountLoader<'info, ClaimBitmapShard>),
+    Record(&'a mut Account<'info, ClaimRecord>, ClaimMode),
 }
 
-#[derive(Accounts)]
-#[instruction(user_count: u32)]
-pub struct ActivateQuest<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(mut)]
-    pub quest: Account<'info, QuestAccount>,
-    /// CHECK: 动态大小的位图
-    #[account(init_if_needed, payer = admin, space = 8 + 32 + 2 + 4 + 4 + 4 + 1, seeds = [b"bitmap", quest.key().as_ref()], bump)]
-    pub bitmap_shard: Account<'info, ClaimBitmapShard>,
-    pub system_program: Program<'info, System>,
+// 校验领取条件（状态、时间窗、默克尔证明、总额），按记账方式计算本次可领金额，记账后向 recipient 放款
+fn process_claim(
+    accounts: ClaimPayout,
+    user: Pubkey,
+    index: u64,
+    amount: u64,
+    proof: &[[u8; 32]],
+) -> Result<()> {
+    let quest = accounts.quest;
+    let phase = accounts.phase;
+    // 暂停对所有阶段生效
+    require!(quest.status == Status::Active, SharelyError::QuestNotActive);
+    let (phase_id, merkle_root, leaf_format, start_at, end_at, user_count) = match &phase {
+        Some(p) => (
+            p.phase_id,
+            p.merkle_root,
+            p.leaf_format,
+            p.start_at,
+            p.end_at,
+            p.user_count,
+        ),
+        None => (
+            0,
+            quest.merkle_root,
+            quest.leaf_format,
+            quest.start_at,
+            quest.end_at,
+            quest.user_count,
+        ),
+    };
+    let now_ts = Clock::get()?.unix_timestamp;
+    require!(now_ts >= start_at, SharelyError::InvalidStatus);
+    require!(now_ts <= end_at, SharelyError::InvalidStatus);
+    require!(amount > 0, SharelyError::InvalidAmount);
+    require!(
+        proof.len() as u8 <= MAX_PROOF_NODES,
+        SharelyError::ProofTooLong
+    );
+
+    let leaf = leaf_hash(leaf_format, &quest.key(), index, user, amount);
+    let computed_root = compute_merkle_root_sorted(leaf_format, leaf, proof);
+    require!(computed_root == merkle_root, SharelyError::InvalidProof);
+    // 检查索引是否在有效范围内
+    require!(
+        index < user_count as u64,
+        SharelyError::BitmapIndexOutOfRange
+    );
+    let amount = match accounts.ledger {
+        ClaimLedger::Bitmap(bitmap_shard) => {
+            require!(
+                quest.claim_mode == ClaimMode::Bitmap,
+                SharelyError::ClaimModeMismatch
+            );
+            // 检查是否已领取并标记，分片由 index 经 seeds 路由
+            set_claimed_bit(bitmap_shard, index % BITS_PER_SHARD as u64)?;
+            amount
+        }
+        ClaimLedger::Record(record, mode) => {
+            require!(quest.claim_mode == mode, SharelyError::ClaimModeMismatch);
+            if record.quest == Pubkey::default() {
+                record.quest = quest.key();
+                record.user = user;
+                record.index = index;
+            }
+            let entitled = match mode {
+                ClaimMode::Vesting => {
+                    require!(record.index == index, SharelyError::AccountMismatch);
+                    quest.vested_amount(amount, now_ts)?
+                }
+                // root 轮换后同一用户的 index 可能变化，记录按用户累计
+                ClaimMode::Cumulative => {
+                    record.index = index;
+                    amount
+                }
+                ClaimMode::Bitmap => return err!(SharelyError::ClaimModeMismatch),
+            };
+            let claimable = entitled.saturating_sub(record.claimed_amount);
+            require!(claimable > 0, SharelyError::NothingToClaim);
+            record.claimed_amount = entitled;
+            claimable
+        }
+    };
+    // 累计领取不得超过激活时承诺的空投总额
+    let claimed_total = quest
+        .claimed_total
+        .checked_add(amount)
+        .ok_or(SharelyError::Overflow)?;
+    require!(
+        claimed_total <= quest.total_allocated,
+        SharelyError::AllocationExceeded
+    );
+    // 追加阶段的领取同时不得超过该阶段的额度
+    if let Some(phase) = phase {
+        phase.claimed_total = phase
+            .claimed_total
+            .checked_add(amount)
+            .ok_or(SharelyError::Overflow)?;
+        require!(
+            phase.claimed_total <= phase.total_allocated,
+            SharelyError::AllocationExceeded
+        );
+    }
+    let bump = accounts.vault_authority_bump;
+    let quest_key = quest.key();
+    let signer_seeds: &[&[u8]] = &[b"vault_auth", quest_key.as_ref(), &[bump]];
+    let transfer_fee = match quest.asset {
+        QuestAsset::Token => {
+            let mint = required(accounts.mint)?;
+            let token_program = required(accounts.token_program)?;
+            let recipient_ata = required(accounts.recipient_ata)?;
+
+            // 验证 mint 地址是否正确
+            require!(mint.key() == quest.mint, SharelyError::AccountMismatch);
+
+            // 验证收款 ATA 地址是否正确
+            let expected_ata = get_associated_token_address_with_program_id(
+                accounts.recipient.key,
+                &mint.key(),
+                &token_program.key(),
+            );
+            require!(
+                recipient_ata.key() == expected_ata,
+                SharelyError::AccountMismatch
+            );
+
+            // 如果收款 ATA 不存在，由 payer 创建
+            if recipient_ata.data_is_empty() {
+                anchor_spl::associated_token::create(CpiContext::new(
+                    required(accounts.associated_token_program)?.to_account_info(),
+                    anchor_spl::associated_token::Create {
+                        payer: accounts.payer.clone(),
+                        associated_token: recipient_ata.to_account_info(),
+                        authority: accounts.recipient.clone(),
+                        mint: mint.to_account_info(),
+                        system_program: accounts.system_program.to_account_info(),
+                        token_program: token_program.to_account_info(),
+                    },
+                ))?;
+            }
+
+            // transfer-fee 由 vault 额外承担，保证用户实际到账 amount
+            let transfer_fee = transfer_fee_for_receipt(&mint.to_account_info(), amount)?;
+            transfer_from_vault(
+                token_program,
+                mint,
+                required(accounts.vault)?,
+                accounts.vault_authority,
+                recipient_ata.to_account_info(),
+                signer_seeds,
+                amount
+                    .checked_add(transfer_fee)
+                    .ok_or(SharelyError::Overflow)?,
+            )?;
+            transfer_fee
+        }
+        QuestAsset::NativeSol => {
+            // lamports 直接转入收款钱包
+            require!(
+                native_vault_balance(accounts.vault_authority)? >= amount,
+                SharelyError::VaultInsufficient
+            );
+            transfer_native_from_vault(
+                accounts.system_program,
+                accounts.vault_authority,
+                accounts.recipient.clone(),
+                signer_seeds,
+                amount,
+            )?;
+            0
+        }
+    };
+    quest.charge_transfer_fee(transfer_fee)?;
+    quest.claimed_total = claimed_total;
+    emit!(Claimed {
+        quest: quest.key(),
+        quest_id: quest.quest_id,
+        user,
+        recipient: accounts.recipient.key(),
+        index,
+        amount,
+        version: quest.version,
+        transfer_fee,
+        phase_id,
+    });
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct Claim<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut)]
-    pub quest: Account<'info, QuestAccount>,
-    /// CHECK: PDA authority
-    #[account(seeds = [b"vault_auth", quest.key().as_ref()], bump)]
-    pub vault_authority: UncheckedAccount<'info>,
-    #[account(mut, address = quest.vault)]
-    pub vault: Account<'info, TokenAccount>,
-    /// CHECK: User's associated token account (will be created if needed)
-    #[account(mut)]
-    pub user_ata: UncheckedAccount<'info>,
-    /// CHECK: Mint account (needed for ATA creation)
-    pub mint: Account<'info, Mint>,
-    /// 动态位图
-    #[account(mut, seeds = [b"bitmap", quest.key().as_ref()], bump)]
-    pub bitmap_shard: Account<'info, ClaimBitmapShard>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+// index 所在的位图分片；index < user_count (u32)，分片号不会超出 u16
+fn bitmap_shard_id(index: u64) -> u16 {
+    (index / BITS_PER_SHARD as u64) as u16
 }
 
-#[derive(Accounts)]
-pub struct IsClaimed<'info> {
-    pub quest: Account<'info, QuestAccount>,
-    #[account(seeds = [b"bitmap", quest.key().as_ref()], bump)]
-    pub bitmap_shard: Account<'info, ClaimBitmapShard>,
-    /// CHECK: user
-    pub user: UncheckedAccount<'info>,
+// 位图分片 seeds 中的分片部分：phase 0 沿用 shard_id，追加阶段再拼接 phase_id
+fn bitmap_shard_seed(shard_id: u16, phase_id: u16) -> Vec<u8> {
+    let mut seed = shard_id.to_le_bytes().to_vec();
+    if phase_id > 0 {
+        seed.extend_from_slice(&phase_id.to_le_bytes());
+    }
+    seed
 }
 
-#[derive(Accounts)]
-pub struct VerifyEligibility<'info> {
-    pub quest: Account<'info, QuestAccount>,
-    /// CHECK: 仅用于身份标识，不读取数据
-    pub user: UncheckedAccount<'info>,
+// user_count 个用户中分片 shard_id 覆盖的用户数（最后一个分片可能不满，超出范围为 0）
+fn shard_user_count(user_count: u32, shard_id: u16) -> u32 {
+    let start = shard_id as u64 * BITS_PER_SHARD as u64;
+    (user_count as u64)
+        .saturating_sub(start)
+        .min(BITS_PER_SHARD as u64) as u32
 }
 
-#[derive(Accounts)]
-pub struct CloseQuestByMerchant<'info> {
-    #[account(mut)]
-    pub merchant: Signer<'info>,
-    #[account(mut, has_one = merchant)]
-    pub quest: Account<'info, QuestAccount>,
-    /// CHECK: PDA authority
-    #[account(seeds = [b"vault_auth", quest.key().as_ref()], bump)]
-    pub vault_authority: UncheckedAccount<'info>,
-    #[account(mut, address = quest.vault)]
-    pub vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub destination_ata: Account<'info, TokenAccount>,
-    #[account(mut, seeds = [b"config"], bump)]
-    pub config: Account<'info, Config>,
-    /// CHECK: 验证 treasury_ata 是否为授权 treasury 地址的 ATA
-    #[account(
-        mut,
-        constraint = treasury_ata.mint == quest.mint @ SharelyError::AccountMismatch,
-        constraint = treasury_ata.owner == config.treasury @ SharelyError::Unauthorized
-    )]
-    pub treasury_ata: Account<'info, TokenAccount>,
-    pub token_program: Program<'info, Token>,
+// 账户是否刚由 init_if_needed 创建（discriminator 尚未写入）
+fn is_uninitialized(info: &AccountInfo) -> Result<bool> {
+    Ok(info.try_borrow_data()?.iter().take(8).all(|b| *b == 0))
 }
 
-#[derive(Accounts)]
-pub struct CancelQuest<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(mut, has_one = admin)]
-    pub quest: Account<'info, QuestAccount>,
-    /// CHECK: PDA authority
-    #[account(seeds = [b"vault_auth", quest.key().as_ref()], bump)]
-    pub vault_authority: UncheckedAccount<'info>,
-    #[account(mut, address = quest.vault)]
-    pub vault: Account<'info, TokenAccount>,
-    /// CHECK: Merchant ATA，验证地址是否正确
-    #[account(
-        mut,
-        constraint = merchant_ata.mint == vault.mint @ SharelyError::AccountMismatch,
-        constraint = merchant_ata.owner == quest.merchant @ SharelyError::AccountMismatch
-    )]
-    pub merchant_ata: Account<'info, TokenAccount>,
-    #[account(mut, seeds = [b"config"], bump)]
-    pub config: Account<'info, Config>,
-    pub token_program: Program<'info, Token>,
+// index 在分片位图中对应字节的账户数据偏移与位掩码
+fn bitmap_position(
+    shard: &AccountLoader<ClaimBitmapShard>,
+    shard_index: u64,
+) -> Result<(usize, u8)> {
+    let user_count = shard.load()?.user_count;
+    require!(
+        shard_index < user_count as u64,
+        SharelyError::BitmapIndexOutOfRange
+    );
+    Ok((
+        ClaimBitmapShard::BITS_OFFSET + (shard_index / 8) as usize,
+        1u8 << (shard_index % 8),
+    ))
 }
 
-#[derive(Accounts)]
-pub struct UpdateTreasury<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    /// CHECK: 全局配置账户
-    #[account(
-        mut,
-        has_one = admin,
-        seeds = [b"config"],
-        bump
-    )]
-    pub config: Account<'info, Config>,
+fn is_bit_set(shard: &AccountLoader<ClaimBitmapShard>, shard_index: u64) -> Result<bool> {
+    let (pos, mask) = bitmap_position(shard, shard_index)?;
+    let data = shard.as_ref().try_borrow_data()?;
+    let byte = data.get(pos).ok_or(SharelyError::BitmapIndexOutOfRange)?;
+    Ok(byte & mask != 0)
 }
 
-// =========================
-// Events
-// =========================
+// 标记已领取，已置位时返回 AlreadyClaimed
+fn set_claimed_bit(shard: &AccountLoader<ClaimBitmapShard>, shard_index: u64) -> Result<()> {
+    let (pos, mask) = bitmap_position(shard, shard_index)?;
+    let mut data = shard.as_ref().try_borrow_mut_data()?;
+    let byte = data
+        .get_mut(pos)
+        .ok_or(SharelyError::BitmapIndexOutOfRange)?;
+    require!(*byte & mask == 0, SharelyError::AlreadyClaimed);
+    *byte |= mask;
+    Ok(())
+}
 
-#[event]
-pub struct Initialized {
-    pub admin: Pubkey,
-    pub treasury: Pubkey,
+// 解析旧版 Borsh 位图，账户不存在时返回 None
+fn read_legacy_bitmap(info: &AccountInfo) -> Result<Option<LegacyClaimBitmapShard>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*info.owner, crate::ID, SharelyError::AccountMismatch);
+    let data = info.try_borrow_data()?;
+    require!(
+        data.get(..8) == Some(ClaimBitmapShard::DISCRIMINATOR),
+        SharelyError::AccountMismatch
+    );
+    let legacy = LegacyClaimBitmapShard::deserialize(&mut &data[8..])
+        .map_err(|_| SharelyError::AccountMismatch)?;
+    Ok(Some(legacy))
 }
 
-#[event]
-pub struct AdminChanged {
-    pub old_admin: Pubkey,
-    pub new_admin: Pubkey,
+// 按分片覆盖的用户数扩容并清零位图分片，扩容所需租金由 payer 补足，返回位图字节数
+fn reset_bitmap_shard<'info>(
+    shard: &AccountLoader<'info, ClaimBitmapShard>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    quest: Pubkey,
+    shard_id: u16,
+    phase_id: u16,
+    user_count: u32,
+) -> Result<u32> {
+    let bitmap_size = user_count.div_ceil(8); // 向上取整到字节
+    resize_bitmap_shard(shard, payer, system_program, user_count)?;
+
+    {
+        // 付费方只在首次创建时记录，之后的扩容租金也随分片关闭退还给它
+        let mut header = if is_uninitialized(shard.as_ref())? {
+            let mut header = shard.load_init()?;
+            header.payer = payer.key();
+            header
+        } else {
+            shard.load_mut()?
+        };
+        header.quest = quest;
+        header.shard_id = shard_id;
+        header.phase_id = phase_id;
+        header.user_count = user_count;
+    }
+    shard.as_ref().try_borrow_mut_data()?[ClaimBitmapShard::BITS_OFFSET..].fill(0);
+    Ok(bitmap_size)
 }
 
-#[event]
-pub struct QuestCreated {
-    pub status: Status,
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub merchant: Pubkey,
-    pub mint: Pubkey,
-    pub total_amount: u64,
+// 将已有分片扩容到 user_count，只清零新增的位图字节，已置位的领取记录保持不变；
+// 头部 user_count 已足够时不做修改，返回位图字节数
+fn grow_bitmap_shard<'info>(
+    shard: &AccountLoader<'info, ClaimBitmapShard>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    user_count: u32,
+) -> Result<u32> {
+    let current_user_count = shard.load()?.user_count;
+    if current_user_count >= user_count {
+        return Ok(current_user_count.div_ceil(8));
+    }
+    resize_bitmap_shard(shard, payer, system_program, user_count)?;
+    let old_end = ClaimBitmapShard::space(current_user_count);
+    shard.as_ref().try_borrow_mut_data()?[old_end..].fill(0);
+    shard.load_mut()?.user_count = user_count;
+    Ok(user_count.div_ceil(8))
 }
 
-#[event]
-pub struct QuestStatusChanged {
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub status: Status,
+// 分片空间不足以容纳 user_count 时重新分配，新增租金由 payer 补足
+fn resize_bitmap_shard<'info>(
+    shard: &AccountLoader<'info, ClaimBitmapShard>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    user_count: u32,
+) -> Result<()> {
+    let required_space = ClaimBitmapShard::space(user_count);
+
+    // 检查空间是否足够，如果不够则重新分配
+    let account_info = shard.to_account_info();
+    let current_space = account_info.data_len();
+    if required_space > current_space {
+        account_info.resize(required_space)?;
+        let additional_lamports = Rent::get()?.minimum_balance(required_space)
+            - Rent::get()?.minimum_balance(current_space);
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account_info,
+                },
+            ),
+            additional_lamports,
+        )?;
+    }
+    Ok(())
 }
 
-#[event]
-pub struct VaultFunded {
-    pub funder: Pubkey,
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub amount: u64,
+// close_quest_by_merchant / close_quest_by_admin 共用的结算账户；
+// destination_ata 为 token quest 剩余资金的接收 ATA，原生 SOL quest 的剩余 lamports 退回 merchant
+struct QuestSettlement<'a, 'info> {
+    rent: QuestRentReclaim<'a, 'info>,
+    system_program: &'a Program<'info, System>,
+    treasury_ata: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    treasury: &'a Option<UncheckedAccount<'info>>,
+    destination_ata: Option<AccountInfo<'info>>,
 }
 
-#[event]
-pub struct QuestActivated {
-    pub status: Status,
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub version: u32,
-    pub merkle_root: [u8; 32],
-    pub start_at: i64,
-    pub end_at: i64,
-    pub fee_amount: u64,
+// 将 fee_amount 转入 treasury，剩余资金退回商户，之后回收租金
+fn settle_closed_quest<'info>(
+    accounts: QuestSettlement<'_, 'info>,
+    signer_seeds: &[&[u8]],
+    remaining: &'info [AccountInfo<'info>],
+    fee_amount: u64,
+) -> Result<()> {
+    let quest = accounts.rent.quest;
+    let vault_authority = accounts.rent.vault_authority;
+
+    if quest.asset == QuestAsset::NativeSol {
+        // 手续费不超过 vault 中剩余的 lamports，避免关闭因余额不足而永久失败
+        let fee_amount = fee_amount.min(vault_authority.lamports());
+        if fee_amount > 0 {
+            let treasury = required(accounts.treasury)?;
+            transfer_native_from_vault(
+                accounts.system_program,
+                vault_authority,
+                treasury.to_account_info(),
+                signer_seeds,
+                fee_amount,
+            )?;
+            emit!(FeeTransferred {
+                quest: quest.key(),
+                quest_id: quest.quest_id,
+                fee_amount,
+                recipient: treasury.key(),
+                fee_policy: quest.fee_policy,
+            });
+        }
+
+        // 剩余 lamports（含免租金额）全部退回商户
+        let amount = vault_authority.lamports();
+        if amount > 0 {
+            transfer_native_from_vault(
+                accounts.system_program,
+                vault_authority,
+                accounts.rent.merchant.clone(),
+                signer_seeds,
+                amount,
+            )?;
+            emit!(QuestClosed {
+                status: quest.status,
+                quest: quest.key(),
+                quest_id: quest.quest_id,
+                remaining_transferred: amount,
+                recipient: accounts.rent.merchant.key(),
+            });
+        }
+        return reclaim_quest_rent(accounts.rent, signer_seeds, remaining, fee_amount, amount);
+    }
+    let token_program = required(accounts.rent.token_program)?;
+    let mint = required(accounts.rent.mint)?;
+    let vault = required(accounts.rent.vault)?;
+
+    // transfer fee amount to treasury, the left amount will be transferred to merchant
+    let mut fee_amount = fee_amount;
+    let mut fee_sent = 0;
+    if fee_amount > 0 {
+        let treasury_ata = required(accounts.treasury_ata)?;
+        // 使用 vault_authority 作为签名者从 vault 转账到 treasury_ata，transfer-fee 由 vault 承担
+        fee_sent = fee_amount
+            .checked_add(transfer_fee_for_receipt(
+                &mint.to_account_info(),
+                fee_amount,
+            )?)
+            .ok_or(SharelyError::Overflow)?;
+        // mint 费率上调超出预留时 vault 可能不足，手续费以 vault 余额为上限，避免关闭永久失败
+        if fee_sent > vault.amount {
+            fee_sent = vault.amount;
+            fee_amount = amount_after_transfer_fee(&mint.to_account_info(), fee_sent)?;
+        }
+        transfer_from_vault(
+            token_program,
+            mint,
+            vault,
+            vault_authority,
+            treasury_ata.to_account_info(),
+            signer_seeds,
+            fee_sent,
+        )?;
+        emit!(FeeTransferred {
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            fee_amount,
+            recipient: treasury_ata.key(),
+            fee_policy: quest.fee_policy,
+        });
+    }
+
+    let mut refunded = 0;
+    if vault.amount > fee_sent {
+        let amount = vault.amount - fee_sent;
+        refunded = amount;
+        let destination_ata = accounts
+            .destination_ata
+            .ok_or_else(|| error!(SharelyError::AssetAccountMismatch))?;
+        let recipient = destination_ata.key();
+        transfer_from_vault(
+            token_program,
+            mint,
+            vault,
+            vault_authority,
+            destination_ata,
+            signer_seeds,
+            amount,
+        )?;
+
+        emit!(QuestClosed {
+            status: quest.status,
+            quest: quest.key(),
+            quest_id: quest.quest_id,
+            remaining_transferred: amount,
+            recipient,
+        });
+    }
+
+    reclaim_quest_rent(accounts.rent, signer_seeds, remaining, fee_amount, refunded)
+}
+
+// close / cancel 回收租金所需的账户，quest 本身由 close 约束关闭
+struct QuestRentReclaim<'a, 'info> {
+    quest: &'a Account<'info, QuestAccount>,
+    vault_authority: &'a UncheckedAccount<'info>,
+    vault: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    mint: &'a Option<InterfaceAccount<'info, Mint>>,
+    token_program: &'a Option<Interface<'info, TokenInterface>>,
+    merchant: AccountInfo<'info>,
+    tombstone: &'a mut Account<'info, QuestTombstone>,
+}
+
+// 资金转出后关闭已清空的 vault ATA，并关闭 remaining_accounts 中属于该 quest 的位图分片与阶段账户；
+// 未传入的分片与阶段可在关闭后通过 close_bitmap_shard / close_quest_phase 回收
+fn reclaim_quest_rent<'info>(
+    accounts: QuestRentReclaim<'_, 'info>,
+    signer_seeds: &[&[u8]],
+    remaining: &'info [AccountInfo<'info>],
+    fee_amount: u64,
+    remaining_transferred: u64,
+) -> Result<()> {
+    let quest = accounts.quest;
+    let quest_key = quest.key();
+
+    let mut vault_rent = 0;
+    if quest.asset == QuestAsset::Token {
+        let vault = required(accounts.vault)?;
+        let mint = required(accounts.mint)?;
+        let token_program = required(accounts.token_program)?;
+        vault_rent = close_vault_ata(
+            token_program,
+            mint,
+            vault,
+            accounts.vault_authority,
+            accounts.merchant.clone(),
+            signer_seeds,
+        )?;
+    }
+
+    // 程序所属的账户为待关闭的位图分片与阶段，其余为各自记录的付费方，租金退还给付费方
+    let mut bitmap_rent: u64 = 0;
+    for info in remaining.iter().filter(|info| *info.owner == crate::ID) {
+        let is_shard = info.try_borrow_data()?.get(..8) == Some(ClaimBitmapShard::DISCRIMINATOR);
+        let (owner_quest, payer) = if is_shard {
+            let header = AccountLoader::<ClaimBitmapShard>::try_from(info)?;
+            let header = header.load()?;
+            (header.quest, header.payer)
+        } else {
+            let phase = Account::<QuestPhase>::try_from(info)?;
+            (phase.quest, phase.payer)
+        };
+        require_keys_eq!(owner_quest, quest_key, SharelyError::AccountMismatch);
+        let destination = remaining
+            .iter()
+            .find(|account| account.key() == payer)
+            .ok_or(SharelyError::AccountMismatch)?;
+        bitmap_rent = bitmap_rent
+            .checked_add(close_program_account(info, destination)?)
+            .ok_or(SharelyError::Overflow)?;
+    }
+
+    let record = accounts.tombstone;
+    record.quest = quest_key;
+    record.quest_id = quest.quest_id;
+    record.merchant = quest.merchant;
+    record.mint = quest.mint;
+    record.status = quest.status;
+    record.funded_amount = quest.funded_amount;
+    record.claimed_total = quest.claimed_total;
+    record.fee_amount = fee_amount
+        .checked_add(quest.fee_paid)
+        .ok_or(SharelyError::Overflow)?;
+    record.remaining_transferred = remaining_transferred;
+    record.closed_at = Clock::get()?.unix_timestamp;
+
+    emit!(QuestRentReclaimed {
+        quest: quest_key,
+        quest_id: quest.quest_id,
+        vault_rent,
+        bitmap_rent,
+        quest_rent: quest.to_account_info().lamports(),
+    });
+    Ok(())
 }
 
-#[event]
-pub struct Claimed {
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub user: Pubkey,
-    pub index: u64,
-    pub amount: u64,
-    pub version: u32,
+// 关闭已清空的 vault ATA，租金退还 destination，返回回收的租金
+fn close_vault_ata<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    vault_authority: &UncheckedAccount<'info>,
+    destination: AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+) -> Result<u64> {
+    harvest_withheld_fees(token_program, mint, vault)?;
+    let vault_rent = vault.to_account_info().lamports();
+    let signer = [signer_seeds];
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        token_interface::CloseAccount {
+            account: vault.to_account_info(),
+            destination,
+            authority: vault_authority.to_account_info(),
+        },
+        &signer,
+    ))?;
+    Ok(vault_rent)
 }
 
-#[event]
-pub struct QuestClosed {
-    pub status: Status,
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub remaining_transferred: u64,
-    pub recipient: Pubkey,
+// cancel_quest / emergency_cancel_quest 共用的取消事件，附带完整的资金记录
+fn emit_quest_cancelled(
+    quest: &Account<QuestAccount>,
+    previous: Status,
+    refunded: u64,
+    emergency: bool,
+) {
+    emit!(QuestCancelled {
+        status: quest.status,
+        quest: quest.key(),
+        quest_id: quest.quest_id,
+        remaining_transferred: refunded,
+        recipient: quest.merchant,
+        previous_status: previous,
+        funded_amount: quest.funded_amount,
+        claimed_total: quest.claimed_total,
+        fee_paid: quest.fee_paid,
+        emergency,
+    });
 }
 
-#[event]
-pub struct QuestCancelled {
-    pub status: Status,
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub remaining_transferred: u64,
-    pub recipient: Pubkey,
+// 将 vault 中 Token-2022 预扣的 transfer-fee 归集到 mint（无需签名），否则 vault 无法关闭
+fn harvest_withheld_fees<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+) -> Result<()> {
+    let vault_info = vault.to_account_info();
+    if *vault_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    let withheld = {
+        let data = vault_info.try_borrow_data()?;
+        let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+        state
+            .get_extension::<TransferFeeAmount>()
+            .map(|fee| u64::from(fee.withheld_amount))
+            .unwrap_or(0)
+    };
+    if withheld == 0 {
+        return Ok(());
+    }
+    let ix = spl_token_2022::extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint(
+        &token_program.key(),
+        &mint.key(),
+        &[&vault.key()],
+    )?;
+    anchor_lang::solana_program::program::invoke(
+        &ix,
+        &[
+            mint.to_account_info(),
+            vault_info,
+            token_program.to_account_info(),
+        ],
+    )?;
+    Ok(())
 }
 
-#[event]
-pub struct BitmapInitialized {
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub user_count: u32,
-    pub bitmap_size: u32,
+// 关闭本程序所有的账户，lamports 转入 destination，返回回收的租金
+fn close_program_account(info: &AccountInfo, destination: &AccountInfo) -> Result<u64> {
+    let lamports = info.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(SharelyError::Overflow)?;
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&anchor_lang::system_program::ID);
+    info.resize(0)?;
+    Ok(lamports)
 }
 
-#[event]
-pub struct TreasuryUpdated {
-    pub new_treasury: Pubkey,
-    pub admin: Pubkey,
+// 所有修改 quest 状态的指令共用的守卫，按 Status::can_transition_to 校验，返回转换前的状态
+fn transition_status(quest: &mut QuestAccount, next: Status) -> Result<Status> {
+    let previous = quest.status;
+    require!(
+        previous.can_transition_to(next),
+        SharelyError::InvalidStatusTransition
+    );
+    quest.status = next;
+    Ok(previous)
 }
 
-#[event]
-pub struct FeeTransferred {
-    pub quest: Pubkey,
-    pub quest_id: u64,
-    pub fee_amount: u64,
-    pub recipient: Pubkey,
+fn require_role(config: &Config, key: &Pubkey, role: Role) -> Result<()> {
+    require!(config.has_role(key, role), SharelyError::Unauthorized);
+    Ok(())
 }
 
-// =========================
-// Errors
-// =========================
-#[error_code]
-pub enum SharelyError {
-    #[msg("Unauthorized")]
-    Unauthorized,
-    #[msg("Invalid status")]
-    InvalidStatus,
-    #[msg("Invalid mint or account mismatch")]
-    AccountMismatch,
-    #[msg("Invalid amount")]
-    InvalidAmount,
-    #[msg("Invalid proof")]
-    InvalidProof,
-    #[msg("Already claimed")]
-    AlreadyClaimed,
-    #[msg("Vault insufficient")]
-    VaultInsufficient,
-    #[msg("Proof too long")]
-    ProofTooLong,
-    #[msg("Bitmap index out of range")]
-    BitmapIndexOutOfRange,
-    #[msg("Quest not active")]
-    QuestNotActive,
-    #[msg("Overflow")]
-    Overflow,
-    #[msg("Invalid argument")]
-    InvalidArgument,
-    #[msg("Invalid ed25519 signature")]
-    InvalidSignature,
-    #[msg("Invalid fee amount")]
-    InvalidFeeAmount,
+// 统计具备 ApprovalSigner 角色的不同签名者，需达到 approval_threshold
+fn verify_approval_threshold(config: &Config, signers: &[Pubkey]) -> Result<()> {
+    let mut approvers: Vec<&Pubkey> = signers
+        .iter()
+        .filter(|s| config.has_role(s, Role::ApprovalSigner))
+        .collect();
+    approvers.sort();
+    approvers.dedup();
+    require!(
+        !approvers.is_empty() && approvers.len() >= config.approval_threshold as usize,
+        SharelyError::InsufficientApprovals
+    );
+    Ok(())
 }
 
-// =========================
-// Helpers
-// =========================
+// ed25519 precompile 指令数据布局：
+// [0] 签名数量 [1] padding，随后每个签名一组 14 字节的 Ed25519SignatureOffsets（均为 u16 LE）
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_SIZE: usize = 14;
+const ED25519_PUBKEY_SIZE: usize = 32;
+const ED25519_SIGNATURE_SIZE: usize = 64;
 
-fn leaf_hash(index: u64, user: Pubkey, amount: u64) -> [u8; 32] {
-    let idx_le = index.to_le_bytes();
-    let amt_le = amount.to_le_bytes();
-    let data: Vec<&[u8]> = vec![&idx_le, user.as_ref(), &amt_le];
-    let h = hashv(&data);
-    h.to_bytes()
+struct Ed25519SignatureOffsets {
+    signature_offset: u16,
+    signature_instruction_index: u16,
+    public_key_offset: u16,
+    public_key_instruction_index: u16,
+    message_data_offset: u16,
+    message_data_size: u16,
+    message_instruction_index: u16,
 }
 
-fn hash_pair_sorted(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
-    if a <= b {
-        hashv(&[&a, &b]).to_bytes()
-    } else {
-        hashv(&[&b, &a]).to_bytes()
+impl Ed25519SignatureOffsets {
+    fn unpack(data: &[u8]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        Self {
+            signature_offset: u16_at(0),
+            signature_instruction_index: u16_at(2),
+            public_key_offset: u16_at(4),
+            public_key_instruction_index: u16_at(6),
+            message_data_offset: u16_at(8),
+            message_data_size: u16_at(10),
+            message_instruction_index: u16_at(12),
+        }
     }
 }
 
-fn compute_merkle_root_sorted(leaf: [u8; 32], proof: &Vec<[u8; 32]>) -> [u8; 32] {
-    let mut acc = leaf;
-    for p in proof.iter() {
-        acc = hash_pair_sorted(acc, *p);
+fn slice_at(data: &[u8], offset: u16, len: usize) -> Result<&[u8]> {
+    let start = offset as usize;
+    data.get(start..start + len)
+        .ok_or_else(|| error!(SharelyError::InvalidEd25519Instruction))
+}
+
+// 解析单条 ed25519 指令（位于交易中 ix_index 处），返回其中对 message 签名的公钥。
+// 所有 offset 必须指向本条指令（u16::MAX 或自身下标），拒绝引用其他指令的数据
+fn parse_ed25519_instruction(data: &[u8], ix_index: u16, message: &[u8]) -> Result<Vec<Pubkey>> {
+    let num_signatures = *data
+        .first()
+        .ok_or(SharelyError::InvalidEd25519Instruction)? as usize;
+    require!(
+        num_signatures > 0
+            && data.len() >= ED25519_OFFSETS_START + num_signatures * ED25519_OFFSETS_SIZE,
+        SharelyError::InvalidEd25519Instruction
+    );
+
+    let is_self = |i: u16| i == u16::MAX || i == ix_index;
+    let mut signers = Vec::new();
+    for n in 0..num_signatures {
+        let start = ED25519_OFFSETS_START + n * ED25519_OFFSETS_SIZE;
+        let offsets = Ed25519SignatureOffsets::unpack(&data[start..start + ED25519_OFFSETS_SIZE]);
+        require!(
+            is_self(offsets.signature_instruction_index)
+                && is_self(offsets.public_key_instruction_index)
+                && is_self(offsets.message_instruction_index),
+            SharelyError::InvalidEd25519Instruction
+        );
+
+        // Solana 的 ed25519 程序已经验证过签名，这里只需确认各字段在指令数据范围内且消息一致
+        slice_at(data, offsets.signature_offset, ED25519_SIGNATURE_SIZE)?;
+        let pubkey_bytes = slice_at(data, offsets.public_key_offset, ED25519_PUBKEY_SIZE)?;
+        let message_bytes = slice_at(
+            data,
+            offsets.message_data_offset,
+            offsets.message_data_size as usize,
+        )?;
+        if message_bytes == message {
+            signers.push(
+                Pubkey::try_from(pubkey_bytes)
+                    .map_err(|_| SharelyError::InvalidEd25519Instruction)?,
+            );
+        }
     }
-    acc
+    Ok(signers)
 }
 
-fn verify_ed25519_signature(
-    instructions_sysvar: &AccountInfo,
-    admin_pubkey: &Pubkey,
-    message: &[u8],
-) -> Result<()> {
-    let mut found = false;
-    let mut idx: usize = 0;
-    loop {
-        let loaded = load_instruction_at_checked(idx, instructions_sysvar);
-        if loaded.is_err() {
+// 返回对 message 做过 ed25519 校验的全部公钥。
+// ed25519 指令必须紧邻当前指令之前（可连续多条），其他位置的校验指令不予采纳
+fn find_ed25519_signers(instructions_sysvar: &AccountInfo, message: &[u8]) -> Result<Vec<Pubkey>> {
+    let current = load_current_index_checked(instructions_sysvar)?;
+    let mut signers = Vec::new();
+    let mut idx = current;
+    while idx > 0 {
+        idx -= 1;
+        let ix = load_instruction_at_checked(idx as usize, instructions_sysvar)?;
+        if ix.program_id != ed25519_program::id() {
             break;
         }
-        let ix = loaded.unwrap();
-        if ix.program_id == ed25519_program::id() {
-            // 验证 ed25519 指令中的公钥和消息
-            if ix.data.len() >= 16 + 64 + 32 {
-                let pubkey_bytes = &ix.data[16..48];
-                let _signature_bytes = &ix.data[48..112];
-                let message_bytes = &ix.data[112..];
-
-                // 检查公钥是否匹配
-                if pubkey_bytes == admin_pubkey.as_ref() && message_bytes == message {
-                    // 这里应该验证签名，但 Solana 的 ed25519 程序已经验证过了
-                    // 我们只需要确保指令存在且参数正确
-                    found = true;
-                    break;
-                }
-            }
-        }
-        idx += 1;
+        signers.extend(parse_ed25519_instruction(&ix.data, idx, message)?);
     }
-    require!(found, SharelyError::InvalidSignature);
-    Ok(())
+    require!(!signers.is_empty(), SharelyError::InvalidSignature);
+    Ok(signers)
 }
 
+// 解析并校验 v2 审批消息，返回商户签约的参数
 fn verify_approval_message(
     approval_bytes: &[u8],
     admin_pubkey: &Pubkey,
@@ -974,29 +4134,607 @@ fn verify_approval_message(
     mint: &Pubkey,
     quest_id: &u64,
     total_amount: &u64,
-) -> Result<()> {
-    // 构建期望的消息内容
-    let domain = hashv(&[b"sharely:v1"]);
-    let expected_message = [
-        domain.as_ref(),
-        admin_pubkey.as_ref(),
-        merchant.as_ref(),
-        mint.as_ref(),
-        &quest_id.to_le_bytes(),
-        &total_amount.to_le_bytes(),
-    ];
-    let expected_bytes = expected_message.concat();
+) -> Result<QuestApproval> {
+    let approval = QuestApproval::try_from_slice(approval_bytes)
+        .map_err(|_| SharelyError::UnsupportedApprovalVersion)?;
+    require!(
+        approval.domain == hashv(&[APPROVAL_DOMAIN_V2]).to_bytes(),
+        SharelyError::UnsupportedApprovalVersion
+    );
 
     // 验证消息内容匹配
     require!(
-        approval_bytes == expected_bytes,
+        approval.program_id == crate::ID
+            && approval.admin == *admin_pubkey
+            && approval.merchant == *merchant
+            && approval.mint == *mint
+            && approval.quest_id == *quest_id
+            && approval.total_amount == *total_amount,
+        SharelyError::InvalidSignature
+    );
+    require!(
+        approval.expires_at >= Clock::get()?.unix_timestamp,
+        SharelyError::ApprovalExpired
+    );
+    require!(
+        approval.end_at > approval.start_at,
+        SharelyError::InvalidArgument
+    );
+    require!(
+        approval.fee_amount <= approval.total_amount,
+        SharelyError::InvalidFeeAmount
+    );
+    Ok(approval)
+}
+
+// 解析并校验追加注资审批消息
+fn verify_funding_approval(
+    approval_bytes: &[u8],
+    quest_key: &Pubkey,
+    quest: &QuestAccount,
+    amount: u64,
+) -> Result<()> {
+    let approval = FundingApproval::try_from_slice(approval_bytes)
+        .map_err(|_| SharelyError::UnsupportedApprovalVersion)?;
+    require!(
+        approval.domain == hashv(&[FUNDING_APPROVAL_DOMAIN_V1]).to_bytes(),
+        SharelyError::UnsupportedApprovalVersion
+    );
+    require!(
+        approval.program_id == crate::ID
+            && approval.quest == *quest_key
+            && approval.merchant == quest.merchant
+            && approval.amount == amount
+            && approval.funded_amount == quest.funded_amount,
+        SharelyError::InvalidSignature
+    );
+    require!(
+        approval.expires_at >= Clock::get()?.unix_timestamp,
+        SharelyError::ApprovalExpired
+    );
+    Ok(())
+}
+
+// 解析并校验代付领取的用户授权消息
+fn verify_claim_authorization(
+    authorization_bytes: &[u8],
+    quest_key: &Pubkey,
+    index: u64,
+    amount: u64,
+    recipient: &Pubkey,
+) -> Result<()> {
+    let authorization = ClaimAuthorization::try_from_slice(authorization_bytes)
+        .map_err(|_| SharelyError::UnsupportedApprovalVersion)?;
+    require!(
+        authorization.domain == hashv(&[CLAIM_AUTHORIZATION_DOMAIN_V1]).to_bytes(),
+        SharelyError::UnsupportedApprovalVersion
+    );
+    require!(
+        authorization.program_id == crate::ID
+            && authorization.quest == *quest_key
+            && authorization.index == index
+            && authorization.amount == amount
+            && authorization.recipient == *recipient,
         SharelyError::InvalidSignature
     );
+    require!(
+        authorization.expires_at >= Clock::get()?.unix_timestamp,
+        SharelyError::ApprovalExpired
+    );
     Ok(())
 }
 
+// 以 vault_authority PDA 签名，从 vault 转出 amount（兼容 Token 与 Token-2022）
+fn transfer_from_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    vault_authority: &UncheckedAccount<'info>,
+    to: AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+    amount: u64,
+) -> Result<()> {
+    let signer = [signer_seeds];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        TransferChecked {
+            from: vault.to_account_info(),
+            mint: mint.to_account_info(),
+            to,
+            authority: vault_authority.to_account_info(),
+        },
+        &signer,
+    );
+    token_interface::transfer_checked(cpi_ctx, amount, mint.decimals)
+}
+
+// 以 vault_authority PDA 签名转出 lamports（原生 SOL quest）
+fn transfer_native_from_vault<'info>(
+    system_program: &Program<'info, System>,
+    vault_authority: &UncheckedAccount<'info>,
+    to: AccountInfo<'info>,
+    signer_seeds: &[&[u8]],
+    amount: u64,
+) -> Result<()> {
+    let signer = [signer_seeds];
+    let cpi_ctx = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        anchor_lang::system_program::Transfer {
+            from: vault_authority.to_account_info(),
+            to,
+        },
+        &signer,
+    );
+    anchor_lang::system_program::transfer(cpi_ctx, amount)
+}
+
+// 原生 SOL quest 可用于领取的 lamports（需保留 0 字节账户的免租金额）
+fn native_vault_balance(vault_authority: &AccountInfo) -> Result<u64> {
+    let reserve = Rent::get()?.minimum_balance(0);
+    Ok(vault_authority.lamports().saturating_sub(reserve))
+}
+
+// 取出当前 quest 资产类型所需的可选账户
+fn required<T>(account: &Option<T>) -> Result<&T> {
+    account
+        .as_ref()
+        .ok_or_else(|| error!(SharelyError::AssetAccountMismatch))
+}
+
+// 读取 Token-2022 mint 的 transfer-fee 配置，普通 SPL Token 或未启用该扩展时返回 None
+fn with_transfer_fee_config<T>(
+    mint: &AccountInfo,
+    f: impl FnOnce(&TransferFeeConfig, u64) -> Option<T>,
+) -> Result<Option<T>> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => {
+            let epoch = Clock::get()?.epoch;
+            Ok(Some(f(config, epoch).ok_or(SharelyError::Overflow)?))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+// 为使接收方恰好到账 amount，发送方需额外支付的 transfer-fee
+fn transfer_fee_for_receipt(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    Ok(with_transfer_fee_config(mint, |config, epoch| {
+        config.calculate_inverse_epoch_fee(epoch, amount)
+    })?
+    .unwrap_or(0))
+}
+
+// vault 以 transfers 笔转账共付出 amount（接收方恰好到账）时最坏情况下承担的 transfer-fee。
+// 只是激活时的预留：实际承担的手续费在每笔转出时由 charge_transfer_fee 记账
+fn transfer_fee_reserve(mint: &AccountInfo, amount: u64, transfers: u64) -> Result<u64> {
+    Ok(with_transfer_fee_config(mint, |config, _| {
+        worst_case_transfer_fee(config, amount, transfers)
+    })?
+    .unwrap_or(0))
+}
+
+// 取 mint 当前与已排期的费率中较高者，按比例部分每笔向上取整最多多 1，且每笔不超过 maximum_fee
+fn worst_case_transfer_fee(config: &TransferFeeConfig, amount: u64, transfers: u64) -> Option<u64> {
+    let worst_case = |fee: &TransferFee| {
+        let bps = u16::from(fee.transfer_fee_basis_points) as u128;
+        let capped = u64::from(fee.maximum_fee) as u128 * transfers as u128;
+        let proportional = if bps >= BPS_DENOMINATOR as u128 {
+            capped
+        } else {
+            let denominator = BPS_DENOMINATOR as u128 - bps;
+            (amount as u128 * bps).div_ceil(denominator) + transfers as u128
+        };
+        u64::try_from(proportional.min(capped)).ok()
+    };
+    Some(worst_case(&config.older_transfer_fee)?.max(worst_case(&config.newer_transfer_fee)?))
+}
+
+// 原生 SOL quest 无 transfer-fee；Token quest 需传入 mint
+fn quest_transfer_fee_reserve(
+    quest: &QuestAccount,
+    mint: &Option<InterfaceAccount<Mint>>,
+    amount: u64,
+    transfers: u64,
+) -> Result<u64> {
+    match quest.asset {
+        QuestAsset::NativeSol => Ok(0),
+        QuestAsset::Token => {
+            transfer_fee_reserve(&required(mint)?.to_account_info(), amount, transfers)
+        }
+    }
+}
+
+// 发送 amount 后接收方实际到账的金额
+fn amount_after_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let fee = with_transfer_fee_config(mint, |config, epoch| {
+        config.calculate_epoch_fee(epoch, amount)
+    })?
+    .unwrap_or(0);
+    amount
+        .checked_sub(fee)
+        .ok_or(error!(SharelyError::Overflow))
+}
+
 // =========================
 // Constants
 // =========================
 
 pub const MAX_PROOF_NODES: u8 = 32;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+// claim_many 单笔最多领取的 quest 数量，受交易账户数与计算单元限制
+pub const MAX_CLAIM_BATCH: usize = 10;
+pub const MERKLE_LEAF_PREFIX: &[u8] = &[0x00];
+pub const MERKLE_NODE_PREFIX: &[u8] = &[0x01];
+// 最初版本 quest 账户的大小（不含审批时间窗、资产类型与 user_count），migrate_quest 据此识别
+pub const LEGACY_QUEST_SPACE: usize = 261;
+// 最初版本 config 账户的大小（discriminator + admin + treasury），migrate_config 据此识别
+pub const LEGACY_CONFIG_SPACE: usize = 72;
+pub const APPROVAL_DOMAIN_V2: &[u8] = b"sharely:v2";
+pub const FUNDING_APPROVAL_DOMAIN_V1: &[u8] = b"sharely:fund:v1";
+pub const CLAIM_AUTHORIZATION_DOMAIN_V1: &[u8] = b"sharely:claim:v1";
+pub const MAX_ROLE_GRANTS: usize = 32;
+// 每个位图分片覆盖的用户数：8 KB 位图，单次扩容不超过 10 KB 限制
+pub const BITS_PER_SHARD: u32 = 65_536;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按 ed25519 程序的布局拼装指令数据：头部 + 每个签名的 offsets，数据区依次为 pubkey、signature、message。
+    // 每项为 (pubkey, message, 三个 instruction_index)
+    fn ed25519_data(entries: &[(Pubkey, &[u8], u16)]) -> Vec<u8> {
+        let mut data = vec![entries.len() as u8, 0];
+        let mut body = Vec::new();
+        let body_start = ED25519_OFFSETS_START + entries.len() * ED25519_OFFSETS_SIZE;
+        for (pubkey, message, ix_index) in entries {
+            let public_key_offset = (body_start + body.len()) as u16;
+            body.extend_from_slice(pubkey.as_ref());
+            let signature_offset = (body_start + body.len()) as u16;
+            body.extend_from_slice(&[7u8; ED25519_SIGNATURE_SIZE]);
+            let message_data_offset = (body_start + body.len()) as u16;
+            body.extend_from_slice(message);
+            for value in [
+                signature_offset,
+                *ix_index,
+                public_key_offset,
+                *ix_index,
+                message_data_offset,
+                message.len() as u16,
+                *ix_index,
+            ] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&body);
+        data
+    }
+
+    fn invalid_ed25519() -> Error {
+        SharelyError::InvalidEd25519Instruction.into()
+    }
+
+    #[test]
+    fn ed25519_single_signature() {
+        let signer = Pubkey::new_unique();
+        let data = ed25519_data(&[(signer, b"approval", u16::MAX)]);
+        assert_eq!(
+            parse_ed25519_instruction(&data, 0, b"approval").unwrap(),
+            vec![signer]
+        );
+        // 消息不一致时不采纳该签名
+        assert!(parse_ed25519_instruction(&data, 0, b"other")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn ed25519_multiple_signatures() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let c = Pubkey::new_unique();
+        let data = ed25519_data(&[(a, b"approval", 2), (b, b"other", 2), (c, b"approval", 2)]);
+        assert_eq!(
+            parse_ed25519_instruction(&data, 2, b"approval").unwrap(),
+            vec![a, c]
+        );
+    }
+
+    #[test]
+    fn ed25519_rejects_cross_instruction_offsets() {
+        let signer = Pubkey::new_unique();
+        let data = ed25519_data(&[(signer, b"approval", 0)]);
+        assert_eq!(
+            parse_ed25519_instruction(&data, 1, b"approval").unwrap_err(),
+            invalid_ed25519()
+        );
+        // 多签名中任意一项引用其他指令即整体拒绝
+        let data = ed25519_data(&[(signer, b"approval", u16::MAX), (signer, b"approval", 0)]);
+        assert_eq!(
+            parse_ed25519_instruction(&data, 1, b"approval").unwrap_err(),
+            invalid_ed25519()
+        );
+    }
+
+    #[test]
+    fn ed25519_rejects_truncated_data() {
+        let signer = Pubkey::new_unique();
+        let data = ed25519_data(&[(signer, b"approval", u16::MAX)]);
+        // message 超出指令数据
+        assert_eq!(
+            parse_ed25519_instruction(&data[..data.len() - 1], 0, b"approval").unwrap_err(),
+            invalid_ed25519()
+        );
+        // offsets 表不完整
+        assert_eq!(
+            parse_ed25519_instruction(&data[..ED25519_OFFSETS_START + 4], 0, b"approval")
+                .unwrap_err(),
+            invalid_ed25519()
+        );
+        // 签名数量为 0 或数据为空
+        assert_eq!(
+            parse_ed25519_instruction(&[0, 0], 0, b"approval").unwrap_err(),
+            invalid_ed25519()
+        );
+        assert_eq!(
+            parse_ed25519_instruction(&[], 0, b"approval").unwrap_err(),
+            invalid_ed25519()
+        );
+        // 声明的签名数量多于 offsets 表
+        let mut overclaimed = data.clone();
+        overclaimed[0] = 40;
+        assert_eq!(
+            parse_ed25519_instruction(&overclaimed, 0, b"approval").unwrap_err(),
+            invalid_ed25519()
+        );
+        // offset 指向数据末尾之外
+        let mut out_of_range = data;
+        let end = (out_of_range.len() as u16).to_le_bytes();
+        out_of_range[ED25519_OFFSETS_START + 4..ED25519_OFFSETS_START + 6].copy_from_slice(&end);
+        assert_eq!(
+            parse_ed25519_instruction(&out_of_range, 0, b"approval").unwrap_err(),
+            invalid_ed25519()
+        );
+    }
+
+    fn hex32(hex: &str) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        out
+    }
+
+    // 期望值按 utils/merkle.ts 中 leafHash / hashPairSorted 的字节布局计算，
+    // 两端任意一侧改动编码都会导致链下生成的 proof 无法通过校验
+    #[test]
+    fn merkle_v2_matches_offchain_hashes() {
+        let quest = Pubkey::new_from_array([1; 32]);
+        let leaf_a = leaf_hash(
+            LeafFormat::V2,
+            &quest,
+            7,
+            Pubkey::new_from_array([2; 32]),
+            1_000_000,
+        );
+        let leaf_b = leaf_hash(
+            LeafFormat::V2,
+            &quest,
+            8,
+            Pubkey::new_from_array([3; 32]),
+            5,
+        );
+        assert_eq!(
+            leaf_a,
+            hex32("906af20ec340a77ae53e1205e9eb3a2bb201c6b6b947ceea05c42a39a7bc80f7")
+        );
+        assert_eq!(
+            leaf_b,
+            hex32("cd9c5ea9d8633271a85d0a155e15fac823779f378bb102eb09967806a9937e96")
+        );
+
+        let root = hex32("efe00d3b2f6978fecdce025262d2c908feddb0e10668f990889cca226d1b626d");
+        assert_eq!(hash_pair_sorted(LeafFormat::V2, leaf_a, leaf_b), root);
+        assert_eq!(hash_pair_sorted(LeafFormat::V2, leaf_b, leaf_a), root);
+        assert_eq!(
+            compute_merkle_root_sorted(LeafFormat::V2, leaf_a, &[leaf_b]),
+            root
+        );
+    }
+
+    #[test]
+    fn merkle_v1_leaf_ignores_quest() {
+        let user = Pubkey::new_from_array([2; 32]);
+        let expected = hex32("83be343a11a120d343f9150f20301935049c5fbaacc48e3c8c9b456045ac7a10");
+        assert_eq!(
+            leaf_hash(LeafFormat::V1, &Pubkey::new_unique(), 7, user, 1_000_000),
+            expected
+        );
+        // V2 叶子绑定 quest，换一个 quest 结果不同
+        assert_ne!(
+            leaf_hash(LeafFormat::V2, &Pubkey::new_unique(), 7, user, 1_000_000),
+            leaf_hash(LeafFormat::V2, &Pubkey::new_unique(), 7, user, 1_000_000)
+        );
+    }
+
+    fn vesting_quest(cliff_at: i64, vesting_start: i64, vesting_end: i64) -> QuestAccount {
+        let zeroed = [0u8; QuestAccount::SPACE - 8];
+        let mut quest = QuestAccount::deserialize(&mut &zeroed[..]).unwrap();
+        quest.claim_mode = ClaimMode::Vesting;
+        quest.cliff_at = cliff_at;
+        quest.vesting_start = vesting_start;
+        quest.vesting_end = vesting_end;
+        quest
+    }
+
+    #[test]
+    fn vesting_before_cliff() {
+        let quest = vesting_quest(100, 50, 250);
+        assert_eq!(quest.vested_amount(1_000, i64::MIN).unwrap(), 0);
+        assert_eq!(quest.vested_amount(1_000, 99).unwrap(), 0);
+        // cliff 时按 vesting_start 起算的进度一次性解锁
+        assert_eq!(quest.vested_amount(1_000, 100).unwrap(), 250);
+    }
+
+    #[test]
+    fn vesting_mid_schedule() {
+        let quest = vesting_quest(100, 50, 250);
+        assert_eq!(quest.vested_amount(1_000, 150).unwrap(), 500);
+        assert_eq!(quest.vested_amount(1_000, 151).unwrap(), 505);
+        // 向下取整
+        assert_eq!(quest.vested_amount(7, 150).unwrap(), 3);
+        // 大额不溢出
+        assert_eq!(quest.vested_amount(u64::MAX, 150).unwrap(), u64::MAX / 2);
+        // cliff 早于 vesting_start 时，开始前解锁为 0
+        let quest = vesting_quest(0, 100, 200);
+        assert_eq!(quest.vested_amount(1_000, 50).unwrap(), 0);
+    }
+
+    #[test]
+    fn vesting_after_end() {
+        let quest = vesting_quest(100, 50, 250);
+        assert_eq!(quest.vested_amount(1_000, 249).unwrap(), 995);
+        assert_eq!(quest.vested_amount(1_000, 250).unwrap(), 1_000);
+        assert_eq!(quest.vested_amount(1_000, i64::MAX).unwrap(), 1_000);
+    }
+
+    fn fee_policy(kind: FeeKind, bps: u16, min_fee: u64, max_fee: u64) -> FeePolicy {
+        FeePolicy {
+            kind,
+            bps,
+            min_fee,
+            max_fee,
+        }
+    }
+
+    #[test]
+    fn fee_flat_ignores_bps_and_caps() {
+        let policy = fee_policy(FeeKind::Flat, 500, 10, 20);
+        assert_eq!(policy.fee_for(1_000, 50_000, 90_000).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn fee_bps_rounds_down() {
+        let claimed = fee_policy(FeeKind::ClaimedBps, 250, 0, 0);
+        // 1_001 * 2.5% = 25.025
+        assert_eq!(claimed.fee_for(0, 1_001, 9_999).unwrap(), 25);
+        assert_eq!(claimed.fee_for(0, 39, 9_999).unwrap(), 0);
+        let funded = fee_policy(FeeKind::FundedBps, 250, 0, 0);
+        assert_eq!(funded.fee_for(0, 1_001, 9_999).unwrap(), 249);
+        // 100% 与大额不溢出
+        let full = fee_policy(FeeKind::FundedBps, BPS_DENOMINATOR as u16, 0, 0);
+        assert_eq!(full.fee_for(0, 0, u64::MAX).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn fee_bps_min_and_max_caps() {
+        let policy = fee_policy(FeeKind::ClaimedBps, 100, 30, 50);
+        assert_eq!(policy.fee_for(0, 0, 0).unwrap(), 30);
+        assert_eq!(policy.fee_for(0, 4_000, 0).unwrap(), 40);
+        assert_eq!(policy.fee_for(0, 1_000_000, 0).unwrap(), 50);
+        // max_fee 为 0 表示不设上限
+        let uncapped = fee_policy(FeeKind::ClaimedBps, 100, 30, 0);
+        assert_eq!(uncapped.fee_for(0, 1_000_000, 0).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn upfront_fee_never_exceeds_settled_fee() {
+        let flat = fee_policy(FeeKind::Flat, 0, 0, 0);
+        assert_eq!(flat.upfront_fee(1_000), 1_000);
+        // bps 策略只预收 min_fee，且不超过 fee_amount
+        let bps = fee_policy(FeeKind::ClaimedBps, 100, 30, 50);
+        assert_eq!(bps.upfront_fee(1_000), 30);
+        assert_eq!(bps.upfront_fee(20), 20);
+        for claimed in [0, 4_000, 1_000_000] {
+            let settled = bps.fee_for(1_000, claimed, 0).unwrap().min(1_000);
+            assert!(bps.upfront_fee(1_000) <= settled);
+        }
+    }
+
+    fn transfer_fee_config(older: (u16, u64), newer: (u16, u64)) -> TransferFeeConfig {
+        let fee = |(bps, maximum_fee): (u16, u64)| TransferFee {
+            epoch: 0.into(),
+            maximum_fee: maximum_fee.into(),
+            transfer_fee_basis_points: bps.into(),
+        };
+        TransferFeeConfig {
+            older_transfer_fee: fee(older),
+            newer_transfer_fee: fee(newer),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transfer_fee_reserve_worst_case() {
+        let config = transfer_fee_config((100, 10), (100, 10));
+        // 大额单笔受 maximum_fee 限制
+        assert_eq!(worst_case_transfer_fee(&config, 1_000_000, 1), Some(10));
+        assert_eq!(
+            worst_case_transfer_fee(&config, 1_000_000, 100),
+            Some(1_000)
+        );
+        // 小额按比例向上取整，每笔另加 1
+        assert_eq!(worst_case_transfer_fee(&config, 100, 1), Some(3));
+        assert_eq!(worst_case_transfer_fee(&config, 100, 4), Some(6));
+        // 取当前与已排期费率中较高者
+        let scheduled = transfer_fee_config((100, 10), (500, 1_000));
+        assert_eq!(
+            worst_case_transfer_fee(&scheduled, 1_000_000, 1),
+            Some(1_000)
+        );
+        // 无费率时不预留；100% 费率只按 maximum_fee 计
+        let none = transfer_fee_config((0, 0), (0, 0));
+        assert_eq!(worst_case_transfer_fee(&none, 1_000_000, 10), Some(0));
+        let full = transfer_fee_config((10_000, 7), (10_000, 7));
+        assert_eq!(worst_case_transfer_fee(&full, 1_000_000, 3), Some(21));
+    }
+
+    #[test]
+    fn transfer_fee_reserve_covers_split_payouts() {
+        for (bps, maximum_fee) in [(1, u64::MAX), (100, 10), (250, 1_000), (9_999, u64::MAX)] {
+            let config = transfer_fee_config((bps, maximum_fee), (bps, maximum_fee));
+            let fee = config.older_transfer_fee;
+            for parts in [1u64, 3, 7, 100] {
+                let amount = 1_000_003u64;
+                let mut paid = 0u64;
+                for i in 0..parts {
+                    let part = amount / parts + u64::from(i < amount % parts);
+                    paid += fee.calculate_inverse_fee(part).unwrap();
+                }
+                assert!(paid <= worst_case_transfer_fee(&config, amount, parts).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn charge_transfer_fee_uses_reserve_then_funding_headroom() {
+        // 1% / maximum_fee = 10 的 mint，单个 vesting 用户 1_000_000，激活时按 1 笔领取 + 1 笔手续费预留
+        let config = transfer_fee_config((100, 10), (100, 10));
+        let mut quest = vesting_quest(0, 0, 100);
+        quest.total_allocated = 1_000_000;
+        quest.transfer_fee_reserve = worst_case_transfer_fee(&config, 1_000_000, 2).unwrap();
+        assert_eq!(quest.transfer_fee_reserve, 20);
+        quest.funded_amount = quest.committed_amount().unwrap();
+
+        // 预留内的手续费不改变承诺总额
+        quest.charge_transfer_fee(10).unwrap();
+        quest.charge_transfer_fee(10).unwrap();
+        assert_eq!(quest.transfer_fee_reserve, 0);
+        assert_eq!(quest.committed_amount().unwrap(), quest.funded_amount);
+        // 预留用尽后没有额外注资则拒绝，避免挤占剩余的空投额度
+        assert_eq!(
+            quest.clone().charge_transfer_fee(10).unwrap_err(),
+            SharelyError::TransferFeeReserveExceeded.into()
+        );
+        // 追加注资后可继续领取
+        quest.funded_amount += 980;
+        for _ in 0..98 {
+            quest.charge_transfer_fee(10).unwrap();
+        }
+        assert_eq!(quest.transfer_fees_paid, 1_000);
+        assert!(quest.charge_transfer_fee(10).is_err());
+    }
+}
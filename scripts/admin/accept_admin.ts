@@ -0,0 +1,53 @@
+import * as anchor from '@coral-xyz/anchor';
+import { Connection, Keypair } from '@solana/web3.js';
+import { getProgram } from './common';
+
+function parseSecret(json: string | undefined): Uint8Array {
+    if (!json) throw new Error('missing NEW_ADMIN_SECRET_JSON');
+    return new Uint8Array(JSON.parse(json));
+}
+
+(async () => {
+    // 使用被提名的新 Admin 私钥签名接受
+    const url = process.env.RPC_URL || 'http://127.0.0.1:8899';
+    const connection = new Connection(url, 'confirmed');
+    const newAdmin = Keypair.fromSecretKey(parseSecret(process.env.NEW_ADMIN_SECRET_JSON));
+    const provider = new anchor.AnchorProvider(connection, new anchor.Wallet(newAdmin), {
+        commitment: 'confirmed',
+    });
+    anchor.setProvider(provider);
+    const program = getProgram(provider);
+
+    // 派生 config PDA
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    const currentConfig = await (program.account as any).config.fetch(config);
+    console.log('当前 Admin 地址:', currentConfig.admin.toBase58());
+    console.log('待接受的 Admin 地址:', currentConfig.pendingAdmin.toBase58());
+    console.log('当前 Signer:', newAdmin.publicKey.toBase58());
+
+    try {
+        const tx = await (program.methods as any)
+            .acceptAdmin()
+            .accounts({
+                pendingAdmin: newAdmin.publicKey,
+                config: config,
+            } as any)
+            .rpc();
+
+        console.log('✅ Admin 移交完成!');
+        console.log('交易签名:', tx);
+
+        const updatedConfig = await (program.account as any).config.fetch(config);
+        console.log('验证: 更新后的 Admin 地址:', updatedConfig.admin.toBase58());
+    } catch (error) {
+        console.error('❌ 接受失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
@@ -2,11 +2,19 @@ import * as anchor from '@coral-xyz/anchor';
 import { getAdminProvider, getProgram, asPubkey } from './common';
 import dotenv from 'dotenv';
 import { Connection, Keypair } from '@solana/web3.js';
+import { getAssociatedTokenAddress } from '@solana/spl-token';
+import { leafFormatArg, LeafFormat } from '../../utils/merkle';
 
 dotenv.config();
 
 const MERKLE_ROOT_HEX = process.env.MERKLE_ROOT_HEX || '';
 const USER_COUNT = Number(process.env.USER_COUNT || '1000');
+// 须与生成默克尔树时使用的格式一致
+const LEAF_FORMAT = (process.env.LEAF_FORMAT || 'v2') as LeafFormat;
+// 默克尔树所有叶子 amount 之和（admin:gen-merkle 输出），与 fee_amount 之和不得超过已注资金额
+const TOTAL_ALLOCATED = process.env.TOTAL_ALLOCATED || '';
+// 与合约 BITS_PER_SHARD 保持一致
+const BITS_PER_SHARD = 65536;
 
 function hexTo32(hex: string): number[] {
     const b = Buffer.from(hex, 'hex');
@@ -46,14 +54,16 @@ function parseSecret(json: string | undefined): Uint8Array {
     );
 
     console.log('USER_COUNT=', USER_COUNT, typeof USER_COUNT);
-    // 计算位图大小
-    const bitmap_size = Math.ceil(USER_COUNT / 8);
-    console.log(`Setting Merkle root and creating bitmap for ${USER_COUNT} users (${bitmap_size} bytes)`);
+    // 计算位图分片数量（每个分片覆盖 BITS_PER_SHARD 个用户）
+    const shardCount = Math.ceil(USER_COUNT / BITS_PER_SHARD);
+    console.log(`Setting Merkle root and creating bitmap for ${USER_COUNT} users (${shardCount} shards)`);
 
 
-    // 构建位图账户（使用 quest pubkey 作为 seed）
+    // 构建 shard 0 位图账户（seeds: bitmap + quest + shard_id u16 LE），其余分片用 admin:quest:init-shards 创建
+    const shardIdLe = Buffer.alloc(2);
+    shardIdLe.writeUInt16LE(0);
     const [bitmapShard, bitmapBump] = anchor.web3.PublicKey.findProgramAddressSync(
-        [Buffer.from('bitmap'), quest.toBytes()],
+        [Buffer.from('bitmap'), quest.toBytes(), shardIdLe],
         program.programId
     );
 
@@ -68,26 +78,86 @@ function parseSecret(json: string | undefined): Uint8Array {
         console.error('MERKLE_ROOT_HEX must be 64 hex characters (32 bytes)');
         process.exit(1);
     }
+    if (!TOTAL_ALLOCATED) {
+        console.error('请设置 TOTAL_ALLOCATED（默克尔树空投总额）');
+        process.exit(1);
+    }
 
-    const start_at = Math.floor(Date.now() / 1000);
-    const end_at = start_at + 3600 * 24 * 1;
-    const fee_amount = 10000000;
+    // 需落在商户审批的时间窗内、手续费不超过审批值；未设置时使用默认值
+    const start_at = Number(process.env.START_AT || Math.floor(Date.now() / 1000));
+    const end_at = Number(process.env.END_AT || start_at + 3600 * 24 * 1);
+    const fee_amount = Number(process.env.FEE_AMOUNT || 10000000);
     console.log('start_at =', start_at);
     console.log('end_at =', end_at);
     console.log('fee_amount =', fee_amount);
+
+    // config.fee_upfront 开启时激活即预收手续费（Flat 为 fee_amount，bps 策略为 min_fee），需传入相关账户
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const configAccount = await (program.account as any).config.fetch(config);
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any).questAccount.fetch(quest);
+    const isNative = questAccount.mint.equals(anchor.web3.PublicKey.default);
+    let feeAccounts = {
+        vaultAuthority: null as anchor.web3.PublicKey | null,
+        vault: null as anchor.web3.PublicKey | null,
+        // Token quest 必传 mint，合约据此预留 transfer-fee
+        mint: isNative ? null : (questAccount.mint as anchor.web3.PublicKey | null),
+        treasuryAta: null as anchor.web3.PublicKey | null,
+        treasury: null as anchor.web3.PublicKey | null,
+        tokenProgram: null as anchor.web3.PublicKey | null,
+    };
+    if (configAccount.feeUpfront) {
+        const [vaultAuthority] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('vault_auth'), quest.toBuffer()],
+            program.programId
+        );
+        const treasury = configAccount.treasury;
+        feeAccounts.vaultAuthority = vaultAuthority;
+        if (isNative) {
+            // 原生 SOL quest：手续费直接转入 treasury 地址
+            feeAccounts.treasury = treasury;
+        } else {
+            const mintInfo = await connection.getAccountInfo(questAccount.mint);
+            if (!mintInfo) throw new Error('mint not found');
+            const tokenProgram = mintInfo.owner;
+            feeAccounts = {
+                ...feeAccounts,
+                vault: questAccount.vault,
+                treasuryAta: await getAssociatedTokenAddress(questAccount.mint, treasury, true, tokenProgram),
+                tokenProgram,
+            };
+        }
+        console.log('fee_upfront 已开启，激活时预收手续费至 treasury:', treasury.toBase58());
+    }
+
     const tx = await program.methods
         .activateQuest(
             hexTo32(MERKLE_ROOT_HEX),
             USER_COUNT,
             new anchor.BN(start_at),
             new anchor.BN(end_at),
-            new anchor.BN(fee_amount)
+            new anchor.BN(fee_amount),
+            leafFormatArg(LEAF_FORMAT) as any,
+            new anchor.BN(TOTAL_ALLOCATED)
         )
-        .accounts({ admin, quest, bitmapShard, systemProgram: anchor.web3.SystemProgram.programId })
+        .accounts({
+            admin,
+            quest,
+            bitmapShard,
+            ...feeAccounts,
+            systemProgram: anchor.web3.SystemProgram.programId,
+        } as any)
         .rpc();
 
     console.log('Quest activated');
     console.log('Admin =', admin.toBase58());
+    if (shardCount > 1) {
+        console.log(`USER_COUNT 超过单个分片容量，请运行 admin:quest:init-shards 创建其余 ${shardCount - 1} 个分片`);
+    }
 })();
 
 
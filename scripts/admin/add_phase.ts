@@ -0,0 +1,62 @@
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import * as anchor from '@coral-xyz/anchor';
+import { leafFormatArg, LeafFormat } from '../../utils/merkle';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+const MERKLE_ROOT_HEX = process.env.MERKLE_ROOT_HEX || '';
+const USER_COUNT = Number(process.env.USER_COUNT || '0');
+// 须与生成默克尔树时使用的格式一致
+const LEAF_FORMAT = (process.env.LEAF_FORMAT || 'v2') as LeafFormat;
+// 本阶段默克尔树所有叶子 amount 之和，与已有阶段额度及 fee_amount 之和不得超过已注资金额
+const TOTAL_ALLOCATED = process.env.TOTAL_ALLOCATED || '';
+// 与合约 BITS_PER_SHARD 保持一致
+const BITS_PER_SHARD = 65536;
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    const root = Buffer.from(MERKLE_ROOT_HEX, 'hex');
+    if (!QUEST || root.length !== 32 || USER_COUNT <= 0 || !TOTAL_ALLOCATED) {
+        console.log('请设置 QUEST_PUBKEY、MERKLE_ROOT_HEX、USER_COUNT、TOTAL_ALLOCATED 环境变量');
+        return;
+    }
+
+    const quest = asPubkey(QUEST);
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+    // 阶段 id 从 1 起依次递增
+    const phaseId: number = questAccount.phaseCount + 1;
+    const startAt = Number(process.env.START_AT || Math.floor(Date.now() / 1000));
+    const endAt = Number(process.env.END_AT || startAt + 3600 * 24);
+    console.log(`phase_id=${phaseId}, start_at=${startAt}, end_at=${endAt}`);
+
+    try {
+        const sig = await (program.methods as any)
+            .addQuestPhase(
+                phaseId,
+                [...root],
+                USER_COUNT,
+                new anchor.BN(startAt),
+                new anchor.BN(endAt),
+                leafFormatArg(LEAF_FORMAT),
+                new anchor.BN(TOTAL_ALLOCATED)
+            )
+            .accounts({
+                admin: provider.wallet.publicKey,
+                quest,
+                // Token quest 必传 mint，合约据此预留 transfer-fee
+                mint: questAccount.mint.equals(anchor.web3.PublicKey.default) ? null : questAccount.mint,
+            } as any)
+            .rpc();
+        console.log('Phase added:', sig);
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+
+    const shardCount = Math.ceil(USER_COUNT / BITS_PER_SHARD);
+    if (shardCount > 1) {
+        console.log(`USER_COUNT 超过单个分片容量，请以 PHASE_ID=${phaseId} 运行 admin:quest:init-shards 创建其余 ${shardCount - 1} 个分片`);
+    }
+})();
@@ -26,35 +26,56 @@ function i64ToLeBytes(n: bigint): Uint8Array {
 }
 
 (async () => {
-    const adminSecret = parseSecret(process.env.ADMIN_SECRET_JSON);
-    const admin = Keypair.fromSecretKey(adminSecret);
+    // 签名者：任一具备 ApprovalSigner 角色的审批人（多签时每位审批人各执行一次）
+    const approverSecret = parseSecret(process.env.APPROVER_SECRET_JSON || process.env.ADMIN_SECRET_JSON);
+    const approver = Keypair.fromSecretKey(approverSecret);
+    // 消息中绑定的是 config.admin，而不是签名者本人
+    const admin = process.env.ADMIN_PUBKEY ? new PublicKey(process.env.ADMIN_PUBKEY) : approver.publicKey;
 
     const merchant = new PublicKey(process.env.MERCHANT_PUBKEY || '');
-    const mint = new PublicKey(process.env.MINT_PUBKEY || '');
+    // MINT_PUBKEY=SOL 表示原生 SOL quest，审批消息中的 mint 为全 0
+    const mint = process.env.MINT_PUBKEY === 'SOL' ? PublicKey.default : new PublicKey(process.env.MINT_PUBKEY || '');
 
+    const programId = new PublicKey(process.env.PROGRAM_ID || '');
     const questId = BigInt(process.env.QUEST_ID || '0');
     const totalAmount = BigInt(process.env.TOTAL_AMOUNT || '0');
-    const domain = sha256(new TextEncoder().encode('sharely:v1'));
+    const startAt = BigInt(process.env.START_AT || '0');
+    const endAt = BigInt(process.env.END_AT || '0');
+    const feeAmount = BigInt(process.env.FEE_AMOUNT || '0');
+    // 审批有效期，默认 1 天
+    const expiresAt = BigInt(process.env.APPROVAL_EXPIRES_AT || String(Math.floor(Date.now() / 1000) + 86400));
+
+    // v2 审批消息，字段顺序与链上 QuestApproval 一致
+    const domain = sha256(new TextEncoder().encode('sharely:v2'));
     const message = new Uint8Array([
         ...domain,
-        ...admin.publicKey.toBytes(),
+        ...programId.toBytes(),
+        ...admin.toBytes(),
         ...merchant.toBytes(),
         ...mint.toBytes(),
         ...u64ToLeBytes(questId),
         ...u64ToLeBytes(totalAmount),
+        ...i64ToLeBytes(startAt),
+        ...i64ToLeBytes(endAt),
+        ...u64ToLeBytes(feeAmount),
+        ...i64ToLeBytes(expiresAt),
     ]);
 
     const nacl = await import('tweetnacl');
-    const sig = nacl.default.sign.detached(message, admin.secretKey);
-    const ok = nacl.default.sign.detached.verify(message, sig, admin.publicKey.toBytes());
+    const sig = nacl.default.sign.detached(message, approver.secretKey);
+    const ok = nacl.default.sign.detached.verify(message, sig, approver.publicKey.toBytes());
     console.log('ok=', ok);
 
-    console.log('ADMIN_PUBKEY=', admin.publicKey.toBase58());
+    console.log('ADMIN_PUBKEY=', admin.toBase58());
+    console.log('APPROVER_PUBKEY=', approver.publicKey.toBase58());
     console.log('MESSAGE_BASE58=', bs58.encode(message));
     console.log('SIGNATURE_BASE58=', bs58.encode(sig));
     console.log('SIGNATURE=', sig);
     console.log('questId=', questId);
     console.log('totalAmount=', totalAmount);
+    console.log('startAt=', startAt, 'endAt=', endAt);
+    console.log('feeAmount=', feeAmount);
+    console.log('expiresAt=', expiresAt);
     console.log('merchant=', merchant.toBase58());
 
 })();
@@ -1,6 +1,6 @@
 import * as anchor from '@coral-xyz/anchor';
 import { getAdminProvider, getProgram, asPubkey } from '../admin/common';
-import { TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+import { getAssociatedTokenAddress } from '@solana/spl-token';
 
 const QUEST = process.env.QUEST_PUBKEY || '';
 const DESTINATION_ATA = process.env.DESTINATION_ATA || '';
@@ -20,10 +20,34 @@ const DESTINATION_ATA = process.env.DESTINATION_ATA || '';
     // 如果未指定目标 ATA，则使用管理员钱包的 ATA
     const MERCHANT_PUBKEY = process.env.MERCHANT_PUBKEY || '';
     const merchant = asPubkey(MERCHANT_PUBKEY);
-    let destinationAta = await getAssociatedTokenAddress(mint, merchant, true);
-    console.log('destinationAta=', destinationAta.toBase58());
+    // 原生 SOL quest（mint 为全 0）：lamports 直接退回商户钱包
+    const isNative = mint.equals(anchor.web3.PublicKey.default);
+    let tokenAccounts = {
+        vault: null as anchor.web3.PublicKey | null,
+        mint: null as anchor.web3.PublicKey | null,
+        merchantAta: null as anchor.web3.PublicKey | null,
+        tokenProgram: null as anchor.web3.PublicKey | null,
+    };
+    if (!isNative) {
+        // Token 或 Token-2022，由 mint 的 owner 决定
+        const mintInfo = await provider.connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        const merchantAta = await getAssociatedTokenAddress(mint, merchant, true, tokenProgram);
+        tokenAccounts = { vault, mint, merchantAta, tokenProgram };
+    }
+    const destination = tokenAccounts.merchantAta ?? merchant;
+    console.log('destination=', destination.toBase58());
 
 
+    // quest 与 vault ATA 的租金退还商户；位图分片与阶段账户不在此交易中传入，
+    // 关闭后通过 yarn admin:quest:close-rent 分批关闭并退还各自记录的付费方
+    // tombstone 为必传的审计记录，同时阻止同一 quest_id 被重新创建
+    const [tombstone] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('tombstone'), quest.toBuffer()],
+        program.programId
+    );
+
     // 取消 Quest 并把剩余资金转走（需要是 quest 的管理员）
     // merchant_ata 从 quest 账户中获取 merchant 地址计算
     await (program.methods as any)
@@ -32,13 +56,15 @@ const DESTINATION_ATA = process.env.DESTINATION_ATA || '';
             admin: provider.wallet.publicKey, // 管理员签名
             quest,
             vaultAuthority: questAccount.vaultAuthority,
-            vault,
-            merchantAta: destinationAta,
-            tokenProgram: TOKEN_PROGRAM_ID,
+            ...tokenAccounts,
+            merchant,
+            tombstone,
+            systemProgram: anchor.web3.SystemProgram.programId,
         } as any)
         .rpc();
     console.log('Quest cancelled');
-    console.log('Remaining funds transferred to:', destinationAta.toBase58());
+    console.log('Remaining funds transferred to:', destination.toBase58());
+    console.log('位图分片与阶段账户的租金请运行 yarn admin:quest:close-rent 回收');
 })();
 
 
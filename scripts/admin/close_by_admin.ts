@@ -0,0 +1,107 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST) {
+        console.error('请设置 QUEST_PUBKEY 环境变量');
+        process.exit(1);
+    }
+
+    const quest = asPubkey(QUEST);
+
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+    const mint = questAccount.mint;
+    const merchant = questAccount.merchant;
+
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const configAccount = await (program.account as any).config.fetch(config);
+    const treasury = configAccount.treasury;
+
+    // 领取窗口结束（含追加阶段）+ 宽限期之后才能由 admin 关闭
+    const windowEnd = Math.max(questAccount.endAt.toNumber(), questAccount.phasesEndAt.toNumber());
+    const closeAfter = windowEnd + configAccount.closeGracePeriod.toNumber();
+    const now = Math.floor(Date.now() / 1000);
+    if (now <= closeAfter) {
+        console.error(`宽限期未结束，${new Date(closeAfter * 1000).toISOString()} 之后才能关闭`);
+        process.exit(1);
+    }
+
+    // 原生 SOL quest（mint 为全 0）：手续费转入 treasury 地址，剩余 lamports 退回商户钱包
+    const isNative = mint.equals(anchor.web3.PublicKey.default);
+    let tokenAccounts = {
+        vault: null as anchor.web3.PublicKey | null,
+        mint: null as anchor.web3.PublicKey | null,
+        merchantAta: null as anchor.web3.PublicKey | null,
+        treasuryAta: null as anchor.web3.PublicKey | null,
+        tokenProgram: null as anchor.web3.PublicKey | null,
+        associatedTokenProgram: null as anchor.web3.PublicKey | null,
+    };
+    if (!isNative) {
+        // Token 或 Token-2022，由 mint 的 owner 决定
+        const mintInfo = await provider.connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        // 剩余资金只能退回商户的 ATA，不存在时由合约创建
+        const merchantAta = await getAssociatedTokenAddress(mint, merchant, true, tokenProgram);
+        const treasuryAta = await getAssociatedTokenAddress(mint, treasury, true, tokenProgram);
+        tokenAccounts = {
+            vault: questAccount.vault,
+            mint,
+            merchantAta,
+            treasuryAta,
+            tokenProgram,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        };
+    }
+
+    console.log('Admin 关闭 Quest:');
+    console.log('Quest:', quest.toBase58());
+    console.log('Merchant:', merchant.toBase58());
+    console.log('Treasury:', treasury.toBase58());
+    console.log('Merchant ATA:', tokenAccounts.merchantAta?.toBase58() ?? '(native SOL)');
+
+    // quest 与 vault ATA 的租金退还商户；位图分片与阶段账户不在此交易中传入，
+    // 关闭后通过 yarn admin:quest:close-rent 分批关闭并退还各自记录的付费方
+    // tombstone 为必传的审计记录，同时阻止同一 quest_id 被重新创建
+    const [tombstone] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('tombstone'), quest.toBuffer()],
+        program.programId
+    );
+
+    try {
+        const tx = await (program.methods as any)
+            .closeQuestByAdmin()
+            .accounts({
+                admin: provider.wallet.publicKey,
+                quest,
+                vaultAuthority: questAccount.vaultAuthority,
+                ...tokenAccounts,
+                merchant,
+                config,
+                treasury: isNative ? treasury : null,
+                tombstone,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            } as any)
+            .rpc();
+        console.log('✅ Quest 已由 admin 关闭');
+        console.log('交易签名:', tx);
+        console.log('位图分片与阶段账户的租金请运行 yarn admin:quest:close-rent 回收');
+    } catch (error) {
+        console.error('❌ 关闭失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
@@ -0,0 +1,54 @@
+import { getAdminProvider, getProgram, asPubkey, questRentAccounts } from './common';
+import * as anchor from '@coral-xyz/anchor';
+import { Transaction } from '@solana/web3.js';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+// 每笔交易关闭的分片 / 阶段账户数量，过大时单笔交易无法容纳
+const RENT_ACCOUNTS_PER_TX = Number(process.env.RENT_ACCOUNTS_PER_TX || '8');
+
+// quest 关闭或取消后（tombstone 已创建）分批关闭剩余的位图分片与阶段账户，
+// 租金退还各自记录的付费方；无需权限，任何钱包都可发起
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST) {
+        console.log('请设置 QUEST_PUBKEY 环境变量');
+        return;
+    }
+
+    const quest = asPubkey(QUEST);
+    const [tombstone] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('tombstone'), quest.toBuffer()],
+        program.programId
+    );
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    if (!(await (program.account as any).questTombstone.fetchNullable(tombstone))) {
+        console.error('tombstone 不存在，quest 尚未关闭或取消');
+        process.exit(1);
+    }
+
+    const pending = await questRentAccounts(program, quest);
+    console.log('待关闭账户数量:', pending.length);
+
+    for (let i = 0; i < pending.length; i += RENT_ACCOUNTS_PER_TX) {
+        const batch = pending.slice(i, i + RENT_ACCOUNTS_PER_TX);
+        const tx = new Transaction();
+        for (const { pubkey, kind, payer } of batch) {
+            const method = kind === 'claimBitmapShard' ? 'closeBitmapShard' : 'closeQuestPhase';
+            const account = kind === 'claimBitmapShard' ? { bitmapShard: pubkey } : { phase: pubkey };
+            tx.add(
+                await (program.methods as any)[method]()
+                    .accounts({ quest, tombstone, ...account, payer } as any)
+                    .instruction()
+            );
+        }
+        try {
+            const sig = await provider.sendAndConfirm(tx);
+            console.log(`closed ${batch.map((a) => a.pubkey.toBase58()).join(',')}:`, sig);
+        } catch (error) {
+            console.error('操作失败:', error);
+            process.exit(1);
+        }
+    }
+})();
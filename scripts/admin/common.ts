@@ -70,3 +70,21 @@ export function asPubkey(s: string): PublicKey {
 }
 
 
+// quest 关闭或取消后仍未回收租金的位图分片与阶段账户及其付费方，按 quest 字段（紧随 8 字节 discriminator）过滤，
+// quest 账户关闭后同样可用
+export async function questRentAccounts(
+    program: anchor.Program<any>,
+    quest: PublicKey
+): Promise<{ pubkey: PublicKey; kind: 'claimBitmapShard' | 'questPhase'; payer: PublicKey }[]> {
+    const filters = [{ memcmp: { offset: 8, bytes: quest.toBase58() } }];
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const accounts = program.account as any;
+    const shards = await accounts.claimBitmapShard.all(filters);
+    const phases = await accounts.questPhase.all(filters);
+    return [
+        // eslint-disable-next-line @typescript-eslint/no-explicit-any
+        ...shards.map((a: any) => ({ pubkey: a.publicKey, kind: 'claimBitmapShard' as const, payer: a.account.payer })),
+        // eslint-disable-next-line @typescript-eslint/no-explicit-any
+        ...phases.map((a: any) => ({ pubkey: a.publicKey, kind: 'questPhase' as const, payer: a.account.payer })),
+    ];
+}
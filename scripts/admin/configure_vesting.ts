@@ -0,0 +1,38 @@
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import * as anchor from '@coral-xyz/anchor';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+// 单位：unix 秒；须满足 vesting_start <= cliff_at <= vesting_end，且 vesting_end 不晚于 quest 结束时间
+const CLIFF_AT = process.env.CLIFF_AT || '';
+const VESTING_START = process.env.VESTING_START || '';
+const VESTING_END = process.env.VESTING_END || '';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST || !CLIFF_AT || !VESTING_START || !VESTING_END) {
+        console.log('请设置 QUEST_PUBKEY、CLIFF_AT、VESTING_START、VESTING_END 环境变量');
+        return;
+    }
+
+    const quest = asPubkey(QUEST);
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+    try {
+        const sig = await (program.methods as any)
+            .configureVesting(new anchor.BN(CLIFF_AT), new anchor.BN(VESTING_START), new anchor.BN(VESTING_END))
+            .accounts({
+                admin: provider.wallet.publicKey,
+                quest,
+                config,
+            } as any)
+            .rpc();
+        console.log('Vesting configured:', sig);
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+})();
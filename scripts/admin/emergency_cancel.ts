@@ -0,0 +1,72 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import { getAssociatedTokenAddress } from '@solana/spl-token';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST) {
+        console.error('请设置 QUEST_PUBKEY 环境变量');
+        process.exit(1);
+    }
+
+    const quest = asPubkey(QUEST);
+
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+    const mint = questAccount.mint;
+    const merchant = questAccount.merchant;
+
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    // 原生 SOL quest（mint 为全 0）：lamports 直接退回商户钱包
+    const isNative = mint.equals(anchor.web3.PublicKey.default);
+    let tokenAccounts = {
+        vault: null as anchor.web3.PublicKey | null,
+        mint: null as anchor.web3.PublicKey | null,
+        merchantAta: null as anchor.web3.PublicKey | null,
+        tokenProgram: null as anchor.web3.PublicKey | null,
+    };
+    if (!isNative) {
+        // Token 或 Token-2022，由 mint 的 owner 决定
+        const mintInfo = await provider.connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        const merchantAta = await getAssociatedTokenAddress(mint, merchant, true, tokenProgram);
+        tokenAccounts = { vault: questAccount.vault, mint, merchantAta, tokenProgram };
+    }
+
+    console.log('紧急取消 Quest:', quest.toBase58());
+    console.log('已领取总额:', questAccount.claimedTotal.toString());
+    console.log('退款地址:', (tokenAccounts.merchantAta ?? merchant).toBase58());
+
+    // quest 与位图账户保留为 Cancelled 状态，已领取的用户无法再次领取
+    try {
+        const tx = await (program.methods as any)
+            .emergencyCancelQuest()
+            .accounts({
+                admin: provider.wallet.publicKey,
+                quest,
+                vaultAuthority: questAccount.vaultAuthority,
+                ...tokenAccounts,
+                merchant,
+                config,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            } as any)
+            .rpc();
+        console.log('✅ Quest 已紧急取消，未领取余额已退回商户');
+        console.log('交易签名:', tx);
+    } catch (error) {
+        console.error('❌ 取消失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
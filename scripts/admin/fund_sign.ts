@@ -0,0 +1,55 @@
+import { Keypair, PublicKey } from '@solana/web3.js';
+import { sha256 } from '@noble/hashes/sha2';
+import bs58 from 'bs58';
+import dotenv from 'dotenv';
+
+dotenv.config();
+
+function parseSecret(json: string | undefined): Uint8Array {
+    if (!json) throw new Error('missing ADMIN_SECRET_JSON');
+    return new Uint8Array(JSON.parse(json));
+}
+
+function u64ToLeBytes(n: bigint): Uint8Array {
+    const buf = new Uint8Array(8);
+    new DataView(buf.buffer).setBigUint64(0, n, true);
+    return buf;
+}
+
+function i64ToLeBytes(n: bigint): Uint8Array {
+    const buf = new Uint8Array(8);
+    new DataView(buf.buffer).setBigInt64(0, n, true);
+    return buf;
+}
+
+(async () => {
+    // 签名者：任一具备 ApprovalSigner 角色的审批人（多签时每位审批人各执行一次）
+    const approver = Keypair.fromSecretKey(parseSecret(process.env.APPROVER_SECRET_JSON || process.env.ADMIN_SECRET_JSON));
+
+    const programId = new PublicKey(process.env.PROGRAM_ID || '');
+    const quest = new PublicKey(process.env.QUEST_PUBKEY || '');
+    const merchant = new PublicKey(process.env.MERCHANT_PUBKEY || '');
+    const amount = BigInt(process.env.FUND_AMOUNT || '0');
+    // 注资前链上的 funded_amount，保证审批只能使用一次
+    const fundedAmount = BigInt(process.env.CURRENT_FUNDED_AMOUNT || '0');
+    const expiresAt = BigInt(process.env.APPROVAL_EXPIRES_AT || String(Math.floor(Date.now() / 1000) + 86400));
+
+    // 字段顺序与链上 FundingApproval 一致
+    const domain = sha256(new TextEncoder().encode('sharely:fund:v1'));
+    const message = new Uint8Array([
+        ...domain,
+        ...programId.toBytes(),
+        ...quest.toBytes(),
+        ...merchant.toBytes(),
+        ...u64ToLeBytes(amount),
+        ...u64ToLeBytes(fundedAmount),
+        ...i64ToLeBytes(expiresAt),
+    ]);
+
+    const nacl = await import('tweetnacl');
+    const sig = nacl.default.sign.detached(message, approver.secretKey);
+
+    console.log('APPROVER_PUBKEY=', approver.publicKey.toBase58());
+    console.log('FUND_MESSAGE_BASE58=', bs58.encode(message));
+    console.log('SIGNATURE_BASE58=', bs58.encode(sig));
+})();
@@ -1,5 +1,6 @@
 import { PublicKey } from '@solana/web3.js';
-import { buildMerkle, leafHash, buf32ToHex } from '../../utils/merkle';
+import 'dotenv/config';
+import { buildMerkle, leafHash, buf32ToHex, LeafFormat } from '../../utils/merkle';
 
 type Entry = { user: string; amount: string; index: number };
 
@@ -19,10 +20,15 @@ entries.forEach((e, i) => {
 
 console.log('entries=', entries);
 
+// v2（默认）叶子绑定 quest 地址，需设置 QUEST_PUBKEY；v1 仅用于旧 quest
+const format = (process.env.LEAF_FORMAT || 'v2') as LeafFormat;
+const quest = format === 'v2' ? new PublicKey(process.env.QUEST_PUBKEY || '') : undefined;
+console.log('leaf_format=', format);
+
 const leaves = entries.map((e, i) =>
-    leafHash(BigInt(e.index), new PublicKey(e.user), BigInt(e.amount))
+    leafHash(BigInt(e.index), new PublicKey(e.user), BigInt(e.amount), format, quest)
 );
-const tree = buildMerkle(leaves);
+const tree = buildMerkle(leaves, format);
 
 // 打印所有叶子节点 index, user, amount, proof
 
@@ -36,6 +42,8 @@ for (let i = 0; i < leaves.length; i++) {
 
 
 console.log('merkle_root(hex)=', buf32ToHex(tree.getRoot()));
+// activate_quest 的 total_allocated
+console.log('total_allocated=', entries.reduce((sum, e) => sum + BigInt(e.amount), 0n).toString());
 
 
 
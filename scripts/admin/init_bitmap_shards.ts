@@ -0,0 +1,72 @@
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import * as anchor from '@coral-xyz/anchor';
+import { Transaction } from '@solana/web3.js';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+// 0 为 activate_quest 设置的 root，追加阶段从 1 开始
+const PHASE_ID = Number(process.env.PHASE_ID || '0');
+// 与合约 BITS_PER_SHARD 保持一致
+const BITS_PER_SHARD = 65536;
+// 每笔交易创建的分片数量
+const SHARDS_PER_TX = Number(process.env.SHARDS_PER_TX || '4');
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST) {
+        console.log('请设置 QUEST_PUBKEY 环境变量');
+        return;
+    }
+
+    const quest = asPubkey(QUEST);
+    const phaseIdLe = Buffer.alloc(2);
+    phaseIdLe.writeUInt16LE(PHASE_ID);
+    const phase = PHASE_ID > 0
+        ? anchor.web3.PublicKey.findProgramAddressSync([Buffer.from('phase'), quest.toBuffer(), phaseIdLe], program.programId)[0]
+        : null;
+    const accountName = phase ? 'questPhase' : 'questAccount';
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const data = await (program.account as any)[accountName].fetch(phase ?? quest);
+    const userCount: number = data.userCount;
+    const shardCount = Math.ceil(userCount / BITS_PER_SHARD);
+    console.log(`user_count=${userCount}, shard_count=${shardCount}`);
+
+    // shard 0 已由 activate_quest / add_quest_phase 创建或重置；其余分片不存在，
+    // 或头部 user_count 小于当前所需人数（如重新激活前创建）时需要创建或扩容
+    const pending: number[] = [];
+    for (let shardId = 1; shardId < shardCount; shardId++) {
+        const shardIdLe = Buffer.alloc(2);
+        shardIdLe.writeUInt16LE(shardId);
+        const shardSeed = phase ? Buffer.concat([shardIdLe, phaseIdLe]) : shardIdLe;
+        const [bitmapShard] = anchor.web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('bitmap'), quest.toBuffer(), shardSeed],
+            program.programId
+        );
+        const expected = Math.min(userCount - shardId * BITS_PER_SHARD, BITS_PER_SHARD);
+        // eslint-disable-next-line @typescript-eslint/no-explicit-any
+        const shard = await (program.account as any).claimBitmapShard.fetchNullable(bitmapShard);
+        if (!shard || shard.userCount < expected) pending.push(shardId);
+    }
+    console.log('待创建或扩容分片:', pending);
+
+    for (let i = 0; i < pending.length; i += SHARDS_PER_TX) {
+        const batch = pending.slice(i, i + SHARDS_PER_TX);
+        const tx = new Transaction();
+        for (const shardId of batch) {
+            tx.add(
+                await (program.methods as any)
+                    .initBitmapShard(shardId, PHASE_ID)
+                    .accounts({ admin: provider.wallet.publicKey, quest, phase } as any)
+                    .instruction()
+            );
+        }
+        try {
+            const sig = await provider.sendAndConfirm(tx);
+            console.log(`shards ${batch.join(',')} initialized:`, sig);
+        } catch (error) {
+            console.error('操作失败:', error);
+            process.exit(1);
+        }
+    }
+})();
@@ -0,0 +1,49 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram } from './common';
+
+// 最初版本 config 账户的大小（discriminator + admin + treasury），与合约 LEGACY_CONFIG_SPACE 保持一致
+const LEGACY_CONFIG_SPACE = 72;
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    const configInfo = await provider.connection.getAccountInfo(config);
+    if (!configInfo) {
+        console.error('config 不存在，请先运行 admin:init:config');
+        process.exit(1);
+    }
+    if (configInfo.data.length !== LEGACY_CONFIG_SPACE) {
+        console.log('config 已是最新布局');
+        return;
+    }
+
+    try {
+        const tx = await (program.methods as any)
+            .migrateConfig()
+            .accounts({
+                admin: provider.wallet.publicKey,
+                config,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            } as any)
+            .rpc();
+        console.log('✅ config 已迁移到当前布局!');
+        console.log('交易签名:', tx);
+
+        const updatedConfig = await (program.account as any).config.fetch(config);
+        console.log('验证: admin =', updatedConfig.admin.toBase58());
+        console.log('验证: treasury =', updatedConfig.treasury.toBase58());
+        console.log('验证: approval_threshold =', updatedConfig.approvalThreshold);
+    } catch (error) {
+        console.error('❌ 迁移失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
@@ -0,0 +1,56 @@
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import * as anchor from '@coral-xyz/anchor';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+// 与合约 BITS_PER_SHARD 保持一致
+const BITS_PER_SHARD = 65536;
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST) {
+        console.log('请设置 QUEST_PUBKEY 环境变量');
+        return;
+    }
+
+    const quest = asPubkey(QUEST);
+    const [legacyBitmap] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('bitmap'), quest.toBuffer()],
+        program.programId
+    );
+
+    try {
+        // 1) 扩容 quest 账户到当前布局（已迁移则跳过）
+        const questInfo = await provider.connection.getAccountInfo(quest);
+        if (!questInfo) throw new Error('quest not found');
+        // eslint-disable-next-line @typescript-eslint/no-explicit-any
+        const questSpace = (program.account as any)["questAccount"].size;
+        if (questInfo.data.length < questSpace) {
+            const sig = await (program.methods as any)
+                .migrateQuest()
+                .accounts({ admin: provider.wallet.publicKey, quest, legacyBitmap } as any)
+                .rpc();
+            console.log('quest migrated:', sig);
+        } else {
+            console.log('quest 已是最新布局');
+        }
+
+        // 2) 旧版位图按分片从后往前迁移，全部迁移后旧账户被关闭
+        // eslint-disable-next-line @typescript-eslint/no-explicit-any
+        const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+        const userCount: number = questAccount.userCount;
+        for (let shardId = Math.ceil(userCount / BITS_PER_SHARD) - 1; shardId >= 0; shardId--) {
+            if (!(await provider.connection.getAccountInfo(legacyBitmap))) break;
+            const sig = await (program.methods as any)
+                .migrateBitmap(shardId)
+                .accounts({ admin: provider.wallet.publicKey, quest, legacyBitmap } as any)
+                .rpc();
+            console.log(`shard ${shardId} migrated:`, sig);
+        }
+        console.log('迁移完成');
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+})();
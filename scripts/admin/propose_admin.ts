@@ -30,29 +30,28 @@ const NEW_ADMIN = process.env.NEW_ADMIN_PUBKEY || '';
         console.warn('请先运行 init_global_config.ts 初始化全局配置');
     }
 
-    console.log('更改 Admin 地址...');
+    console.log('提名新 Admin（需新 Admin 执行 accept_admin.ts 后生效）...');
     console.log('当前 Signer:', provider.wallet.publicKey.toBase58());
     console.log('新 Admin:', newAdmin.toBase58());
     console.log('Config PDA:', config.toBase58());
 
     try {
         const tx = await (program.methods as any)
-            .changeAdmin(newAdmin)
+            .proposeAdmin(newAdmin)
             .accounts({
-                signer: provider.wallet.publicKey,
+                admin: provider.wallet.publicKey,
                 config: config,
             } as any)
             .rpc();
 
-        console.log('✅ Admin 地址更改成功!');
+        console.log('✅ 新 Admin 提名成功!');
         console.log('交易签名:', tx);
-        console.log('新 Admin 地址:', newAdmin.toBase58());
 
         // 验证更新后的配置
         const updatedConfig = await (program.account as any).config.fetch(config);
-        console.log('验证: 更新后的 Admin 地址:', updatedConfig.admin.toBase58());
+        console.log('验证: 待接受的 Admin 地址:', updatedConfig.pendingAdmin.toBase58());
     } catch (error) {
-        console.error('❌ 更改失败:', error);
+        console.error('❌ 提名失败:', error);
         if ((error as any).logs) {
             console.error('错误日志:', (error as any).logs);
         }
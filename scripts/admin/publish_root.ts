@@ -0,0 +1,52 @@
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import * as anchor from '@coral-xyz/anchor';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+const MERKLE_ROOT_HEX = process.env.MERKLE_ROOT_HEX || '';
+const USER_COUNT = Number(process.env.USER_COUNT || '0');
+// 新 root 中所有叶子累计金额之和，不得小于当前值
+const TOTAL_ALLOCATED = process.env.TOTAL_ALLOCATED || '';
+// 设为 true 时先将 quest 切换为累计分发模式（仅在发生任何领取之前）
+const ENABLE_CUMULATIVE = process.env.ENABLE_CUMULATIVE === 'true';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST) {
+        console.log('请设置 QUEST_PUBKEY 环境变量');
+        return;
+    }
+
+    const quest = asPubkey(QUEST);
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+    const accounts = { admin: provider.wallet.publicKey, quest, config } as any;
+    try {
+        if (ENABLE_CUMULATIVE) {
+            const sig = await (program.methods as any).configureCumulative().accounts(accounts).rpc();
+            console.log('Cumulative mode enabled:', sig);
+            return;
+        }
+
+        const root = Buffer.from(MERKLE_ROOT_HEX, 'hex');
+        if (root.length !== 32 || USER_COUNT <= 0 || !TOTAL_ALLOCATED) {
+            console.log('请设置 MERKLE_ROOT_HEX、USER_COUNT、TOTAL_ALLOCATED 环境变量');
+            return;
+        }
+        // eslint-disable-next-line @typescript-eslint/no-explicit-any
+        const questAccount = await (program.account as any).questAccount.fetch(quest);
+        // Token quest 必传 mint，合约据此预留 transfer-fee
+        const mint = questAccount.mint.equals(anchor.web3.PublicKey.default) ? null : questAccount.mint;
+        const sig = await (program.methods as any)
+            .publishRoot([...root], USER_COUNT, new anchor.BN(TOTAL_ALLOCATED))
+            .accounts({ ...accounts, mint })
+            .rpc();
+        console.log('Root published:', sig);
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+})();
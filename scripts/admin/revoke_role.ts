@@ -0,0 +1,50 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram, asPubkey } from './common';
+
+const MEMBER = process.env.ROLE_MEMBER_PUBKEY || '';
+// approvalSigner | questOperator | treasurer
+const ROLE = process.env.ROLE || '';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!MEMBER || !ROLE) {
+        console.error('请设置 ROLE_MEMBER_PUBKEY 与 ROLE 环境变量');
+        console.error('示例: ROLE=questOperator（可选 approvalSigner / questOperator / treasurer）');
+        process.exit(1);
+    }
+
+    const member = asPubkey(MEMBER);
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    console.log('撤销角色...');
+    console.log('成员:', member.toBase58());
+    console.log('角色:', ROLE);
+
+    try {
+        const tx = await (program.methods as any)
+            .revokeRole(member, { [ROLE]: {} })
+            .accounts({
+                admin: provider.wallet.publicKey,
+                config: config,
+            } as any)
+            .rpc();
+        console.log('✅ 角色撤销成功!');
+        console.log('交易签名:', tx);
+
+        const updatedConfig = await (program.account as any).config.fetch(config);
+        for (const grant of updatedConfig.roles) {
+            console.log(' -', grant.member.toBase58(), Object.keys(grant.role)[0]);
+        }
+    } catch (error) {
+        console.error('❌ 撤销失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
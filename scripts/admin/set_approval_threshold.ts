@@ -0,0 +1,40 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram } from './common';
+
+const THRESHOLD = Number(process.env.APPROVAL_THRESHOLD || '0');
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!THRESHOLD) {
+        console.error('请设置 APPROVAL_THRESHOLD 环境变量，例如 APPROVAL_THRESHOLD=2');
+        process.exit(1);
+    }
+
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    try {
+        const tx = await (program.methods as any)
+            .setApprovalThreshold(THRESHOLD)
+            .accounts({
+                admin: provider.wallet.publicKey,
+                config: config,
+            } as any)
+            .rpc();
+        console.log('✅ 审批门限已更新!');
+        console.log('交易签名:', tx);
+
+        const updatedConfig = await (program.account as any).config.fetch(config);
+        console.log('验证: 当前审批门限:', updatedConfig.approvalThreshold);
+    } catch (error) {
+        console.error('❌ 更新失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
@@ -0,0 +1,41 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram } from './common';
+
+// 领取窗口结束后 admin 代关 quest 前需等待的秒数
+const CLOSE_GRACE_PERIOD = process.env.CLOSE_GRACE_PERIOD || '';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!CLOSE_GRACE_PERIOD) {
+        console.error('请设置 CLOSE_GRACE_PERIOD 环境变量（秒），例如 CLOSE_GRACE_PERIOD=2592000');
+        process.exit(1);
+    }
+
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    try {
+        const tx = await (program.methods as any)
+            .setCloseGracePeriod(new anchor.BN(CLOSE_GRACE_PERIOD))
+            .accounts({
+                admin: provider.wallet.publicKey,
+                config,
+            } as any)
+            .rpc();
+        console.log('✅ 关闭宽限期已更新!');
+        console.log('交易签名:', tx);
+
+        const updatedConfig = await (program.account as any).config.fetch(config);
+        console.log('验证: close_grace_period =', updatedConfig.closeGracePeriod.toString());
+    } catch (error) {
+        console.error('❌ 更新失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
@@ -0,0 +1,66 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram, asPubkey } from './common';
+
+// FEE_KIND: flat | claimedBps | fundedBps
+const FEE_KIND = process.env.FEE_KIND || 'flat';
+const FEE_BPS = Number(process.env.FEE_BPS || '0');
+const MIN_FEE = process.env.MIN_FEE || '0';
+const MAX_FEE = process.env.MAX_FEE || '0';
+// 填写时只修改该 quest 的策略，否则修改全局默认策略
+const QUEST = process.env.QUEST_PUBKEY || '';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!['flat', 'claimedBps', 'fundedBps'].includes(FEE_KIND)) {
+        console.error('FEE_KIND 仅支持 flat / claimedBps / fundedBps');
+        process.exit(1);
+    }
+
+    const feePolicy = {
+        kind: { [FEE_KIND]: {} },
+        bps: FEE_BPS,
+        minFee: new anchor.BN(MIN_FEE),
+        maxFee: new anchor.BN(MAX_FEE),
+    };
+
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    console.log('手续费策略:', FEE_KIND, 'bps:', FEE_BPS, 'min:', MIN_FEE, 'max:', MAX_FEE);
+
+    try {
+        let tx: string;
+        if (QUEST) {
+            const quest = asPubkey(QUEST);
+            tx = await (program.methods as any)
+                .setQuestFeePolicy(feePolicy)
+                .accounts({
+                    admin: provider.wallet.publicKey,
+                    quest,
+                    config,
+                } as any)
+                .rpc();
+            console.log('✅ Quest 手续费策略已更新:', quest.toBase58());
+        } else {
+            tx = await (program.methods as any)
+                .setFeePolicy(feePolicy)
+                .accounts({
+                    admin: provider.wallet.publicKey,
+                    config,
+                } as any)
+                .rpc();
+            console.log('✅ 全局手续费策略已更新（仅影响之后创建的 quest）');
+        }
+        console.log('交易签名:', tx);
+    } catch (error) {
+        console.error('❌ 更新失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
@@ -0,0 +1,41 @@
+import * as anchor from '@coral-xyz/anchor';
+import { getAdminProvider, getProgram } from './common';
+
+// true 时 activate_quest 即预收手续费策略保证收取的部分（Flat 为 fee_amount，bps 策略为 min_fee）
+const FEE_UPFRONT = process.env.FEE_UPFRONT || '';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (FEE_UPFRONT !== 'true' && FEE_UPFRONT !== 'false') {
+        console.error('请设置 FEE_UPFRONT 环境变量，例如 FEE_UPFRONT=true');
+        process.exit(1);
+    }
+
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+
+    try {
+        const tx = await (program.methods as any)
+            .setFeeUpfront(FEE_UPFRONT === 'true')
+            .accounts({
+                admin: provider.wallet.publicKey,
+                config,
+            } as any)
+            .rpc();
+        console.log('✅ 预收手续费设置已更新!');
+        console.log('交易签名:', tx);
+
+        const updatedConfig = await (program.account as any).config.fetch(config);
+        console.log('验证: fee_upfront =', updatedConfig.feeUpfront);
+    } catch (error) {
+        console.error('❌ 更新失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
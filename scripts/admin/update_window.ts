@@ -0,0 +1,43 @@
+import { getAdminProvider, getProgram, asPubkey } from './common';
+import * as anchor from '@coral-xyz/anchor';
+import { Keypair } from '@solana/web3.js';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+const END_AT = Number(process.env.END_AT || '0');
+// 延长到审批时间窗之外时需商户联署，设为 true 时使用 MERCHANT_SECRET_JSON 签名
+const MERCHANT_COSIGN = process.env.MERCHANT_COSIGN === 'true';
+
+(async () => {
+    const provider = getAdminProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST || END_AT <= 0) {
+        console.log('请设置 QUEST_PUBKEY、END_AT 环境变量');
+        return;
+    }
+
+    const quest = asPubkey(QUEST);
+    const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('config')],
+        program.programId
+    );
+    const signers: Keypair[] = [];
+    let merchant = null;
+    if (MERCHANT_COSIGN) {
+        const merchantKp = Keypair.fromSecretKey(new Uint8Array(JSON.parse(process.env.MERCHANT_SECRET_JSON || '[]')));
+        signers.push(merchantKp);
+        merchant = merchantKp.publicKey;
+    }
+
+    try {
+        const sig = await (program.methods as any)
+            .updateQuestWindow(new anchor.BN(END_AT))
+            .accounts({ admin: provider.wallet.publicKey, quest, merchant, config } as any)
+            .signers(signers)
+            .rpc();
+        console.log('Quest window updated:', sig);
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+})();
@@ -203,9 +203,11 @@ class QuestFetcher {
      */
     private async getBitmapInfo(questPubkey: PublicKey): Promise<any | null> {
         try {
-            // 计算 ClaimBitmapShard PDA
+            // 计算 ClaimBitmapShard PDA（shard 0）
+            const shardIdLe = Buffer.alloc(2);
+            shardIdLe.writeUInt16LE(0);
             const [bitmapShard] = PublicKey.findProgramAddressSync(
-                [Buffer.from('bitmap'), questPubkey.toBuffer()],
+                [Buffer.from('bitmap'), questPubkey.toBuffer(), shardIdLe],
                 this.programId
             );
 
@@ -84,8 +84,14 @@ const questClosedLayout = borsh.struct([
 const questCancelledLayout = borsh.struct([
     borsh.u8("status"),
     borsh.publicKey("quest"),
+    borsh.u64("quest_id"),
     borsh.u64("remaining_transferred"),
     borsh.publicKey("recipient"),
+    borsh.u8("previous_status"),
+    borsh.u64("funded_amount"),
+    borsh.u64("claimed_total"),
+    borsh.u64("fee_paid"),
+    borsh.bool("emergency"),
 ]);
 
 const globalConfigInitializedLayout = borsh.struct([
@@ -354,8 +360,14 @@ function decodeQuestCancelled(base64data: string) {
         status: statusMap[decoded.status as keyof typeof statusMap] || 'Unknown',
         statusCode: decoded.status,
         quest: new PublicKey(decoded.quest).toBase58(),
+        questId: Number(decoded.quest_id),
         remainingTransferred: Number(decoded.remaining_transferred),
         recipient: new PublicKey(decoded.recipient).toBase58(),
+        previousStatus: statusMap[decoded.previous_status as keyof typeof statusMap] || 'Unknown',
+        fundedAmount: Number(decoded.funded_amount),
+        claimedTotal: Number(decoded.claimed_total),
+        feePaid: Number(decoded.fee_paid),
+        emergency: decoded.emergency,
     };
 }
 
@@ -1156,6 +1168,9 @@ class ReadOnlyQuestScanner {
                 if (event.recipient) {
                     console.log(`${indent}  Recipient: ${event.recipient}`);
                 }
+                console.log(`${indent}  Previous Status: ${event.previousStatus}`);
+                console.log(`${indent}  Funded / Claimed / Fee Paid: ${event.fundedAmount} / ${event.claimedTotal} / ${event.feePaid}`);
+                console.log(`${indent}  Emergency: ${event.emergency}`);
                 break;
 
             case 'GlobalConfigInitialized':
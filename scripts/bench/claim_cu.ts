@@ -0,0 +1,88 @@
+import 'dotenv/config';
+import * as anchor from '@coral-xyz/anchor';
+import { PublicKey, Connection, Keypair, Transaction } from '@solana/web3.js';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+import { hexTo32ByteArray } from '../../utils/merkle';
+
+// 模拟 claim 交易并输出消耗的计算单元（CU），不会上链。
+// 参数与 user:claim 相同；USER_COUNT 仅用于输出，便于对比不同位图规模下的结果
+(async () => {
+    const connection = new Connection(process.env.RPC_URL || 'http://127.0.0.1:8899', 'confirmed');
+    const secret = new Uint8Array(JSON.parse(process.env.USER_SECRET_JSON || '[]'));
+    const wallet = new anchor.Wallet(Keypair.fromSecretKey(secret));
+    const provider = new anchor.AnchorProvider(connection, wallet, { commitment: 'confirmed' });
+    anchor.setProvider(provider);
+
+    // eslint-disable-next-line @typescript-eslint/no-var-requires
+    const idl = require('../../target/idl/sharely_contract.json');
+    const program = new anchor.Program(idl as anchor.Idl, provider);
+
+    const quest = new PublicKey(process.env.QUEST_PUBKEY || '');
+    const index = Number(process.env.INDEX || '0');
+    const phaseId = Number(process.env.PHASE_ID || '0');
+    const amount = new anchor.BN(process.env.AMOUNT || '0');
+    const proof: number[][] = JSON.parse(process.env.PROOF_JSON || '[]').map(hexTo32ByteArray);
+    const user = wallet.publicKey;
+
+    const phaseIdLe = Buffer.alloc(2);
+    phaseIdLe.writeUInt16LE(phaseId);
+    const shardIdLe = Buffer.alloc(2);
+    shardIdLe.writeUInt16LE(Math.floor(index / 65536));
+    const shardSeed = phaseId > 0 ? Buffer.concat([shardIdLe, phaseIdLe]) : shardIdLe;
+    const [bitmapShard] = PublicKey.findProgramAddressSync(
+        [Buffer.from('bitmap'), quest.toBuffer(), shardSeed],
+        program.programId
+    );
+    const phase = phaseId > 0
+        ? PublicKey.findProgramAddressSync([Buffer.from('phase'), quest.toBuffer(), phaseIdLe], program.programId)[0]
+        : null;
+    const [vaultAuthority] = PublicKey.findProgramAddressSync(
+        [Buffer.from('vault_auth'), quest.toBuffer()],
+        program.programId
+    );
+
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+    const mint: PublicKey = questAccount.mint;
+    let tokenAccounts = {
+        vault: null as PublicKey | null,
+        userAta: null as PublicKey | null,
+        mint: null as PublicKey | null,
+        tokenProgram: null as PublicKey | null,
+        associatedTokenProgram: null as PublicKey | null,
+    };
+    if (!mint.equals(PublicKey.default)) {
+        const mintInfo = await connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        const userAta = await getAssociatedTokenAddress(mint, user, true, tokenProgram);
+        tokenAccounts = { vault: questAccount.vault, userAta, mint, tokenProgram, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID };
+    }
+
+    const ix = await program.methods
+        .claim(phaseId, new anchor.BN(index), amount, proof)
+        .accounts({
+            user,
+            quest,
+            phase,
+            vaultAuthority,
+            ...tokenAccounts,
+            bitmapShard,
+            systemProgram: anchor.web3.SystemProgram.programId,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        } as any)
+        .instruction();
+    const tx = new Transaction().add(ix);
+    tx.feePayer = user;
+    tx.recentBlockhash = (await connection.getLatestBlockhash()).blockhash;
+    tx.sign(wallet.payer);
+
+    const sim = await connection.simulateTransaction(tx);
+    if (sim.value.err) {
+        console.error('模拟失败:', sim.value.err);
+        console.error(sim.value.logs);
+        process.exit(1);
+    }
+    console.log(`user_count=${questAccount.userCount}, index=${index}, proof_len=${proof.length}`);
+    console.log('unitsConsumed =', sim.value.unitsConsumed);
+})();
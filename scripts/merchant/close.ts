@@ -1,6 +1,6 @@
 import * as anchor from '@coral-xyz/anchor';
 import { getMerchantProvider, getProgram, asPubkey } from '../admin/common';
-import { TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+import { getAssociatedTokenAddress } from '@solana/spl-token';
 
 const QUEST = process.env.QUEST_PUBKEY || '';
 const DESTINATION_ATA = process.env.DESTINATION_ATA || '';
@@ -33,26 +33,51 @@ const DESTINATION_ATA = process.env.DESTINATION_ATA || '';
     const globalConfigAccount = await (program.account as any).config.fetch(globalConfig);
     const treasury = globalConfigAccount.treasury;
 
-    // 计算 treasury_ata (treasury 的 ATA)
-    const treasuryAta = await getAssociatedTokenAddress(mint, treasury, true);
+    // 原生 SOL quest（mint 为全 0）：手续费转入 treasury 地址，剩余 lamports 退回商户钱包
+    const isNative = mint.equals(anchor.web3.PublicKey.default);
+    let tokenAccounts = {
+        vault: null as anchor.web3.PublicKey | null,
+        mint: null as anchor.web3.PublicKey | null,
+        destinationAta: null as anchor.web3.PublicKey | null,
+        treasuryAta: null as anchor.web3.PublicKey | null,
+        tokenProgram: null as anchor.web3.PublicKey | null,
+    };
+    if (!isNative) {
+        // 计算 treasury_ata (treasury 的 ATA)
+        // Token 或 Token-2022，由 mint 的 owner 决定
+        const mintInfo = await provider.connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        const treasuryAta = await getAssociatedTokenAddress(mint, treasury, true, tokenProgram);
 
-    // 如果未指定目标 ATA，则使用商户钱包的 ATA
-    let destinationAta;
-    if (DESTINATION_ATA) {
-        destinationAta = asPubkey(DESTINATION_ATA);
-    } else {
-        console.log('Using merchant ATA as destination ATA');
-        const MERCHANT_PUBKEY = process.env.MERCHANT_PUBKEY || '';
-        const merchant = asPubkey(MERCHANT_PUBKEY);
-        destinationAta = await getAssociatedTokenAddress(mint, merchant, true);
+        // 如果未指定目标 ATA，则使用商户钱包的 ATA
+        let destinationAta;
+        if (DESTINATION_ATA) {
+            destinationAta = asPubkey(DESTINATION_ATA);
+        } else {
+            console.log('Using merchant ATA as destination ATA');
+            const MERCHANT_PUBKEY = process.env.MERCHANT_PUBKEY || '';
+            const merchant = asPubkey(MERCHANT_PUBKEY);
+            destinationAta = await getAssociatedTokenAddress(mint, merchant, true, tokenProgram);
+        }
+        tokenAccounts = { vault, mint, destinationAta, treasuryAta, tokenProgram };
     }
 
     console.log('关闭 Quest 信息:');
     console.log('Quest:', quest.toBase58());
     console.log('Merchant:', provider.wallet.publicKey.toBase58());
     console.log('Treasury:', treasury.toBase58());
-    console.log('Treasury ATA:', treasuryAta.toBase58());
-    console.log('Destination ATA:', destinationAta.toBase58());
+    console.log('Treasury ATA:', tokenAccounts.treasuryAta?.toBase58() ?? '(native SOL)');
+    console.log('Destination ATA:', tokenAccounts.destinationAta?.toBase58() ?? '(native SOL)');
+
+    // quest 与 vault ATA 的租金退还商户；位图分片与阶段账户不在此交易中传入，
+    // 关闭后通过 yarn admin:quest:close-rent 分批关闭并退还各自记录的付费方
+    // tombstone 为必传的审计记录，同时阻止同一 quest_id 被重新创建
+    const [tombstone] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('tombstone'), quest.toBuffer()],
+        program.programId
+    );
+    console.log('Tombstone:', tombstone.toBase58());
 
     // 关闭 Quest 并把剩余资金转走（需要是 quest 的商户）
     try {
@@ -62,18 +87,18 @@ const DESTINATION_ATA = process.env.DESTINATION_ATA || '';
                 merchant: provider.wallet.publicKey, // merchant签名
                 quest,
                 vaultAuthority: questAccount.vaultAuthority,
-                vault,
-                destinationAta,
+                ...tokenAccounts,
                 globalConfig,
-                treasuryAta,
-                treasuryAuthority: treasury,
-                tokenProgram: TOKEN_PROGRAM_ID,
+                treasury: isNative ? treasury : null,
+                tombstone,
+                systemProgram: anchor.web3.SystemProgram.programId,
             } as any)
             .rpc();
 
         console.log('✅ Quest closed successfully');
         console.log('交易签名:', tx);
-        console.log('Remaining funds transferred to:', destinationAta.toBase58());
+        console.log('位图分片与阶段账户的租金请运行 yarn admin:quest:close-rent 回收');
+        console.log('Remaining funds transferred to:', (tokenAccounts.destinationAta ?? provider.wallet.publicKey).toBase58());
     } catch (error) {
         console.error('❌ 关闭 Quest 失败:', error);
         if ((error as any).logs) {
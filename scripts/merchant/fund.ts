@@ -0,0 +1,96 @@
+import * as anchor from '@coral-xyz/anchor';
+import { Ed25519Program, PublicKey, Transaction } from '@solana/web3.js';
+import { getAssociatedTokenAddressSync } from '@solana/spl-token';
+import bs58 from 'bs58';
+import { getMerchantProvider, getProgram, asPubkey } from '../admin/common';
+
+const QUEST = process.env.QUEST_PUBKEY || '';
+const FUND_AMOUNT = process.env.FUND_AMOUNT || '0';
+// 可选：config 要求追加注资审批时，由 admin:fund:sign 生成
+const FUND_MESSAGE_BASE58 = process.env.FUND_MESSAGE_BASE58 || '';
+
+(async () => {
+    const provider = getMerchantProvider();
+    const program = getProgram(provider);
+
+    if (!QUEST || FUND_AMOUNT === '0') {
+        console.error('请设置 QUEST_PUBKEY 与 FUND_AMOUNT 环境变量');
+        process.exit(1);
+    }
+
+    const quest = asPubkey(QUEST);
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+    // Token 或 Token-2022，由 mint 的 owner 决定；原生 SOL quest（mint 为全 0）不传 token 相关账户
+    const isNative = questAccount.mint.equals(PublicKey.default);
+    let tokenAccounts = {
+        merchantSourceAta: null as PublicKey | null,
+        vault: null as PublicKey | null,
+        mint: null as PublicKey | null,
+        tokenProgram: null as PublicKey | null,
+    };
+    if (!isNative) {
+        const mintInfo = await provider.connection.getAccountInfo(questAccount.mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        tokenAccounts = {
+            merchantSourceAta: getAssociatedTokenAddressSync(questAccount.mint, provider.wallet.publicKey, true, tokenProgram),
+            vault: questAccount.vault,
+            mint: questAccount.mint,
+            tokenProgram,
+        };
+    }
+
+    // ed25519 校验指令（每位审批人一条，必须紧邻程序指令之前）
+    const ed25519Ixs = [];
+    let approvalBytes: Buffer | null = null;
+    if (FUND_MESSAGE_BASE58) {
+        approvalBytes = Buffer.from(bs58.decode(FUND_MESSAGE_BASE58));
+        const approvers = (process.env.APPROVER_PUBKEYS || process.env.ADMIN_PUBKEY || '')
+            .split(',')
+            .map((s) => new PublicKey(s.trim()));
+        const signatures = (process.env.SIGNATURES_BASE58 || process.env.SIGNATURE_BASE58 || '')
+            .split(',')
+            .map((s) => bs58.decode(s.trim()));
+        if (approvers.length !== signatures.length) throw new Error('APPROVER_PUBKEYS / SIGNATURES_BASE58 数量不一致');
+        for (let i = 0; i < approvers.length; i++) {
+            ed25519Ixs.push(
+                Ed25519Program.createInstructionWithPublicKey({
+                    publicKey: approvers[i].toBytes(),
+                    message: approvalBytes,
+                    signature: signatures[i],
+                    instructionIndex: 0xffff,
+                })
+            );
+        }
+    }
+
+    console.log('追加注资:');
+    console.log('Quest:', quest.toBase58());
+    console.log('Amount:', FUND_AMOUNT);
+    console.log('当前 funded_amount:', questAccount.fundedAmount.toString());
+
+    try {
+        const ix = await (program.methods as any)
+            .fundQuest(new anchor.BN(FUND_AMOUNT), approvalBytes)
+            .accounts({
+                merchant: provider.wallet.publicKey,
+                quest,
+                ...tokenAccounts,
+                vaultAuthority: questAccount.vaultAuthority,
+                instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            } as any)
+            .instruction();
+        const tx = new Transaction().add(...ed25519Ixs, ix);
+        const sig = await provider.sendAndConfirm(tx);
+        console.log('✅ 注资成功!');
+        console.log('交易签名:', sig);
+    } catch (error) {
+        console.error('❌ 注资失败:', error);
+        if ((error as any).logs) {
+            console.error('错误日志:', (error as any).logs);
+        }
+        process.exit(1);
+    }
+})();
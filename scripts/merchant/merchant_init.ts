@@ -2,7 +2,7 @@ import dotenv from 'dotenv';
 import * as anchor from '@coral-xyz/anchor';
 import { Connection, Keypair, PublicKey, Ed25519Program, Transaction } from '@solana/web3.js';
 import bs58 from 'bs58';
-import { TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync } from '@solana/spl-token';
+import { getAssociatedTokenAddressSync } from '@solana/spl-token';
 
 dotenv.config();
 
@@ -17,13 +17,22 @@ function parseSecret(json: string | undefined): Uint8Array {
     const merchantKp = Keypair.fromSecretKey(parseSecret(process.env.MERCHANT_SECRET_JSON));
     const adminPubkey = new PublicKey(process.env.ADMIN_PUBKEY || '');
     const programId = new PublicKey(process.env.PROGRAM_ID || '');
-    const mint = new PublicKey(process.env.MINT_PUBKEY || '');
+    // MINT_PUBKEY=SOL 表示原生 SOL quest，lamports 存放在 vault_authority PDA 上
+    const isNative = process.env.MINT_PUBKEY === 'SOL';
+    const mint = isNative ? PublicKey.default : new PublicKey(process.env.MINT_PUBKEY || '');
 
     const questId = Number(process.env.QUEST_ID || '0');
     const totalAmount = new anchor.BN(process.env.TOTAL_AMOUNT || '0');
 
     const message = bs58.decode(process.env.MESSAGE_BASE58 || '');
-    const signature = bs58.decode(process.env.SIGNATURE_BASE58 || '');
+    // 多签审批：APPROVER_PUBKEYS 与 SIGNATURES_BASE58 按逗号分隔一一对应；单签时回退到 ADMIN_PUBKEY / SIGNATURE_BASE58
+    const approvers = (process.env.APPROVER_PUBKEYS || process.env.ADMIN_PUBKEY || '')
+        .split(',')
+        .map((s) => new PublicKey(s.trim()));
+    const signatures = (process.env.SIGNATURES_BASE58 || process.env.SIGNATURE_BASE58 || '')
+        .split(',')
+        .map((s) => bs58.decode(s.trim()));
+    if (approvers.length !== signatures.length) throw new Error('APPROVER_PUBKEYS / SIGNATURES_BASE58 数量不一致');
 
     const provider = new anchor.AnchorProvider(connection, new anchor.Wallet(merchantKp), { commitment: 'confirmed' });
     anchor.setProvider(provider);
@@ -41,7 +50,7 @@ function parseSecret(json: string | undefined): Uint8Array {
     console.log('questId=', questId);
     console.log('totalAmount=', totalAmount.toString());
     console.log('message=', message);
-    console.log('signature=', signature);
+    console.log('approvers=', approvers.map((a) => a.toBase58()));
     // Derive PDAs（quest_id 使用小端 LE 字节，与 on-chain to_le_bytes 对齐）
     const questIdLe = new Uint8Array(8);
     new DataView(questIdLe.buffer).setBigUint64(0, BigInt(questId), true);
@@ -54,17 +63,37 @@ function parseSecret(json: string | undefined): Uint8Array {
         Buffer.from('vault_auth'),
         quest.toBuffer(),
     ], program.programId);
-    const vault = getAssociatedTokenAddressSync(mint, vaultAuthority, true);
-
-    // Merchant source ATA
-    const merchantAta = getAssociatedTokenAddressSync(mint, merchantKp.publicKey, true);
+    // Token 或 Token-2022，由 mint 的 owner 决定；原生 SOL quest 不传 token 相关账户
+    let tokenAccounts = {
+        merchantSourceAta: null as PublicKey | null,
+        mint: null as PublicKey | null,
+        vault: null as PublicKey | null,
+        tokenProgram: null as PublicKey | null,
+        associatedTokenProgram: null as PublicKey | null,
+    };
+    if (!isNative) {
+        const mintInfo = await connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        tokenAccounts = {
+            merchantSourceAta: getAssociatedTokenAddressSync(mint, merchantKp.publicKey, true, tokenProgram),
+            mint,
+            vault: getAssociatedTokenAddressSync(mint, vaultAuthority, true, tokenProgram),
+            tokenProgram,
+            associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        };
+    }
 
-    // 1) ed25519 verify instruction
-    const ed25519Ix = Ed25519Program.createInstructionWithPublicKey({
-        publicKey: adminPubkey.toBytes(),
-        message,
-        signature,
-    });
+    // 1) ed25519 verify instructions（每位审批人一条，必须紧邻程序指令之前）
+    // instructionIndex=0xffff 表示 offset 指向本条指令自身，合约拒绝引用其他指令的数据
+    const ed25519Ixs = approvers.map((approver, i) =>
+        Ed25519Program.createInstructionWithPublicKey({
+            publicKey: approver.toBytes(),
+            message,
+            signature: signatures[i],
+            instructionIndex: 0xffff,
+        })
+    );
     const PROGRAM_ID = process.env.PROGRAM_ID || '';
     console.log('PROGRAM_ID=', PROGRAM_ID);
     const [config] = anchor.web3.PublicKey.findProgramAddressSync(
@@ -77,21 +106,22 @@ function parseSecret(json: string | undefined): Uint8Array {
         .initializeQuestByMerchant(new anchor.BN(questId), totalAmount, Buffer.from(message))
         .accounts({
             merchant: merchantKp.publicKey,
-            merchantSourceAta: merchantAta,
+            ...tokenAccounts,
             quest,
-            mint,
+            // quest 关闭或取消后留下的 tombstone 须不存在，否则该 quest_id 已使用过
+            tombstone: anchor.web3.PublicKey.findProgramAddressSync(
+                [Buffer.from('tombstone'), quest.toBuffer()],
+                programId
+            )[0],
             vaultAuthority,
-            vault,
             instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
             systemProgram: anchor.web3.SystemProgram.programId,
-            tokenProgram: TOKEN_PROGRAM_ID,
-            associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
             rent: anchor.web3.SYSVAR_RENT_PUBKEY,
             config: config,
-        })
+        } as any)
         .instruction();
 
-    const tx = new Transaction().add(ed25519Ix, ix2);
+    const tx = new Transaction().add(...ed25519Ixs, ix2);
     tx.feePayer = merchantKp.publicKey;
     tx.recentBlockhash = (await connection.getLatestBlockhash('confirmed')).blockhash;
     tx.sign(merchantKp);
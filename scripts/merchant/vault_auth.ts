@@ -2,7 +2,7 @@ import dotenv from 'dotenv';
 import * as anchor from '@coral-xyz/anchor';
 import { Connection, Keypair, PublicKey, Ed25519Program, Transaction } from '@solana/web3.js';
 import bs58 from 'bs58';
-import { TOKEN_PROGRAM_ID, getAssociatedTokenAddressSync } from '@solana/spl-token';
+import { getAssociatedTokenAddressSync } from '@solana/spl-token';
 
 dotenv.config();
 
@@ -48,10 +48,13 @@ function parseSecret(json: string | undefined): Uint8Array {
         quest.toBuffer(),
     ], program.programId);
     console.log('vaultAuthority=', vaultAuthority.toBase58())
-    const vault = getAssociatedTokenAddressSync(mint, vaultAuthority, true);
+    // Token 或 Token-2022，由 mint 的 owner 决定
+    const mintInfo = await connection.getAccountInfo(mint);
+    if (!mintInfo) throw new Error('mint not found');
+    const vault = getAssociatedTokenAddressSync(mint, vaultAuthority, true, mintInfo.owner);
     console.log('vault=', vault.toBase58())
     // Merchant source ATA
-    const merchantAta = getAssociatedTokenAddressSync(mint, merchantKp.publicKey, true);
+    const merchantAta = getAssociatedTokenAddressSync(mint, merchantKp.publicKey, true, mintInfo.owner);
     console.log('merchantAta=', merchantAta.toBase58())
 
 
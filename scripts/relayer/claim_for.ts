@@ -0,0 +1,88 @@
+import 'dotenv/config';
+import * as anchor from '@coral-xyz/anchor';
+import bs58 from 'bs58';
+import { Connection, Ed25519Program, Keypair, PublicKey, Transaction } from '@solana/web3.js';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+import { hexTo32ByteArray } from '../../utils/merkle';
+
+// relayer 代用户提交 claim_for：支付交易费与收款 ATA 租金，用户只需离线签名（user:claim:sign）
+(async () => {
+    const connection = new Connection(process.env.RPC_URL || 'http://127.0.0.1:8899', 'confirmed');
+    const relayerKp = Keypair.fromSecretKey(new Uint8Array(JSON.parse(process.env.RELAYER_SECRET_JSON || '[]')));
+    const wallet = new anchor.Wallet(relayerKp);
+    const provider = new anchor.AnchorProvider(connection, wallet, { commitment: 'confirmed' });
+    anchor.setProvider(provider);
+
+    // eslint-disable-next-line @typescript-eslint/no-var-requires
+    const idl = require('../../target/idl/sharely_contract.json');
+    const program = new anchor.Program(idl as anchor.Idl, provider);
+
+    const quest = new PublicKey(process.env.QUEST_PUBKEY || '');
+    const user = new PublicKey(process.env.USER_PUBKEY || '');
+    const recipient = new PublicKey(process.env.RECIPIENT_PUBKEY || user.toBase58());
+    const index = Number(process.env.INDEX || '0');
+    const amount = new anchor.BN(process.env.AMOUNT || '0');
+    const proof: number[][] = JSON.parse(process.env.PROOF_JSON || '[]').map(hexTo32ByteArray);
+    const message = Buffer.from(bs58.decode(process.env.CLAIM_MESSAGE_BASE58 || ''));
+    const signature = bs58.decode(process.env.CLAIM_SIGNATURE_BASE58 || '');
+
+    const shardIdLe = Buffer.alloc(2);
+    shardIdLe.writeUInt16LE(Math.floor(index / 65536));
+    const [bitmapShard] = PublicKey.findProgramAddressSync(
+        [Buffer.from('bitmap'), quest.toBuffer(), shardIdLe],
+        program.programId
+    );
+    const [vaultAuthority] = PublicKey.findProgramAddressSync(
+        [Buffer.from('vault_auth'), quest.toBuffer()],
+        program.programId
+    );
+
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+    const mint: PublicKey = questAccount.mint;
+    // 原生 SOL quest（mint 为全 0）不传 token 相关账户
+    let tokenAccounts = {
+        vault: null as PublicKey | null,
+        recipientAta: null as PublicKey | null,
+        mint: null as PublicKey | null,
+        tokenProgram: null as PublicKey | null,
+        associatedTokenProgram: null as PublicKey | null,
+    };
+    if (!mint.equals(PublicKey.default)) {
+        const mintInfo = await connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        const recipientAta = await getAssociatedTokenAddress(mint, recipient, true, tokenProgram);
+        tokenAccounts = { vault: questAccount.vault, recipientAta, mint, tokenProgram, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID };
+    }
+
+    // 用户签名的 ed25519 校验指令，必须紧邻 claim_for 之前
+    const ed25519Ix = Ed25519Program.createInstructionWithPublicKey({
+        publicKey: user.toBytes(),
+        message,
+        signature,
+        instructionIndex: 0xffff,
+    });
+
+    try {
+        const ix = await (program.methods as any)
+            .claimFor(new anchor.BN(index), amount, proof, message)
+            .accounts({
+                relayer: wallet.publicKey,
+                user,
+                recipient,
+                quest,
+                vaultAuthority,
+                ...tokenAccounts,
+                bitmapShard,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+            } as any)
+            .instruction();
+        const sig = await provider.sendAndConfirm(new Transaction().add(ed25519Ix, ix));
+        console.log('claim_for tx:', sig);
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+})();
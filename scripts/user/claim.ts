@@ -3,7 +3,7 @@ import * as anchor from '@coral-xyz/anchor';
 import bs58 from 'bs58';
 
 import { PublicKey, Connection, Keypair } from '@solana/web3.js';
-import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
 import { hexTo32ByteArray } from '../../utils/merkle';
 
 (async () => {
@@ -33,18 +33,32 @@ import { hexTo32ByteArray } from '../../utils/merkle';
 
     // 这些参数应来自你的后端
     const quest = new PublicKey(process.env.QUEST_PUBKEY || '');
-    const mint = new PublicKey(process.env.MINT_PUBKEY || '');
     const index = Number(process.env.INDEX || '0');
+    // 领取阶段：0 为 activate_quest 设置的 root，追加阶段从 1 开始
+    const phaseId = Number(process.env.PHASE_ID || '0');
     const amount = new anchor.BN(process.env.AMOUNT || '0');
     const proofHex: string[] = JSON.parse(process.env.PROOF_JSON || '[]');
     const proof: number[][] = proofHex.map(hexTo32ByteArray);
 
     const user = wallet.publicKey;
-
+    // 可选：奖励转入其他钱包（冷钱包、交易所充值地址等），ATA 租金仍由 user 支付
+    const recipient = process.env.RECIPIENT_PUBKEY ? new PublicKey(process.env.RECIPIENT_PUBKEY) : null;
+    const receiver = recipient ?? user;
+    console.log('recipient =', receiver.toString());
+
+    // index 所在的位图分片（与合约 BITS_PER_SHARD = 65536 保持一致），追加阶段的分片 seed 再拼接 phase_id
+    const phaseIdLe = Buffer.alloc(2);
+    phaseIdLe.writeUInt16LE(phaseId);
+    const shardIdLe = Buffer.alloc(2);
+    shardIdLe.writeUInt16LE(Math.floor(index / 65536));
+    const shardSeed = phaseId > 0 ? Buffer.concat([shardIdLe, phaseIdLe]) : shardIdLe;
     const [bitmapShard] = PublicKey.findProgramAddressSync(
-        [Buffer.from('bitmap'), quest.toBuffer()],
+        [Buffer.from('bitmap'), quest.toBuffer(), shardSeed],
         program.programId
     );
+    const phase = phaseId > 0
+        ? PublicKey.findProgramAddressSync([Buffer.from('phase'), quest.toBuffer(), phaseIdLe], program.programId)[0]
+        : null;
 
     // 验证 quest 账户是否存在
     const questInfo = await connection.getAccountInfo(quest);
@@ -58,13 +72,14 @@ import { hexTo32ByteArray } from '../../utils/merkle';
     // eslint-disable-next-line @typescript-eslint/no-explicit-any
     const questAccount = await (program.account as any)["questAccount"].fetch(quest);
     const vault = questAccount.vault;
+    const mint: PublicKey = questAccount.mint;
     console.log('quest account data fetched successfully, vault:', vault.toString());
 
-    // 检查 bitmapShard 账户是否存在（需要通过 activate_quest 初始化）
+    // 检查 bitmapShard 账户是否存在（shard 0 由 activate_quest 创建，其余由 init_bitmap_shard 创建）
     const bitmapShardInfo = await connection.getAccountInfo(bitmapShard);
     if (!bitmapShardInfo) {
         console.error(`Error: bitmapShard account not found at ${bitmapShard.toString()}`);
-        console.error('This quest may not have been activated yet. Please run activate_quest first.');
+        console.error('This quest may not have been activated yet, or this shard has not been created. Please run activate_quest / init_bitmap_shard first.');
         process.exit(1);
     }
     console.log('bitmapShardInfo =', bitmapShardInfo);
@@ -77,8 +92,8 @@ import { hexTo32ByteArray } from '../../utils/merkle';
         process.exit(1);
     }
 
-    // 1) 查询是否已领取
-    try {
+    // 1) 查询是否已领取（is_claimed / verify_eligibility 仅针对 phase 0）
+    if (phaseId === 0) try {
         const claimed = await program.methods
             .isClaimed(new anchor.BN(index))
             .accounts({ quest, bitmapShard, user })
@@ -95,35 +110,50 @@ import { hexTo32ByteArray } from '../../utils/merkle';
     }
 
     // 2) 资格验证（只读）
-    const eligible = await program.methods
-        .verifyEligibility(new anchor.BN(index), amount, proof)
-        .accounts({ quest, user })
-        .view();
-    console.log('isEligible =', eligible);
-    if (!eligible) return;
+    if (phaseId === 0) {
+        const eligible = await program.methods
+            .verifyEligibility(new anchor.BN(index), amount, proof)
+            .accounts({ quest, user })
+            .view();
+        console.log('isEligible =', eligible);
+        if (!eligible) return;
+    }
 
     // 3) 领取
     const [vaultAuthority] = PublicKey.findProgramAddressSync(
         [Buffer.from('vault_auth'), quest.toBuffer()],
         program.programId
     );
-    const userAta = await getAssociatedTokenAddress(mint, user, true);
+    // 原生 SOL quest（mint 为全 0）直接将 lamports 转入收款钱包，不传 token 相关账户
+    let tokenAccounts = {
+        vault: null as PublicKey | null,
+        userAta: null as PublicKey | null,
+        mint: null as PublicKey | null,
+        tokenProgram: null as PublicKey | null,
+        associatedTokenProgram: null as PublicKey | null,
+    };
+    if (!mint.equals(PublicKey.default)) {
+        // Token 或 Token-2022，由 mint 的 owner 决定
+        const mintInfo = await connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        const userAta = await getAssociatedTokenAddress(mint, receiver, true, tokenProgram);
+        tokenAccounts = { vault, userAta, mint, tokenProgram, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID };
+    }
 
     await program.methods
-        .claim(new anchor.BN(index), amount, proof)
+        .claim(phaseId, new anchor.BN(index), amount, proof)
         .accounts({
             user,
+            recipient,
             quest,
+            phase,
             vaultAuthority,
-            vault,
-            userAta,
-            mint,
+            ...tokenAccounts,
             bitmapShard,
-            tokenProgram: TOKEN_PROGRAM_ID,
-            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: anchor.web3.SystemProgram.programId,
             rent: anchor.web3.SYSVAR_RENT_PUBKEY,
-        })
+        } as any)
         .rpc();
 
     console.log('claim tx sent');
@@ -0,0 +1,86 @@
+import 'dotenv/config';
+import * as anchor from '@coral-xyz/anchor';
+import { PublicKey, Connection, Keypair, AccountMeta, ComputeBudgetProgram } from '@solana/web3.js';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+import { hexTo32ByteArray } from '../../utils/merkle';
+
+type ClaimInput = { quest: string; index: number; amount: string; proof: string[] };
+
+// 一笔交易领取多个 quest，CLAIMS_JSON 为 [{ quest, index, amount, proof }]（均来自后端）
+(async () => {
+    const connection = new Connection(process.env.RPC_URL || 'http://127.0.0.1:8899', 'confirmed');
+    const secret = new Uint8Array(JSON.parse(process.env.USER_SECRET_JSON || '[]'));
+    const wallet = new anchor.Wallet(Keypair.fromSecretKey(secret));
+    const provider = new anchor.AnchorProvider(connection, wallet, { commitment: 'confirmed' });
+    anchor.setProvider(provider);
+
+    // eslint-disable-next-line @typescript-eslint/no-var-requires
+    const idl = require('../../target/idl/sharely_contract.json');
+    const program = new anchor.Program(idl as anchor.Idl, provider);
+
+    const user = wallet.publicKey;
+    const recipient = process.env.RECIPIENT_PUBKEY ? new PublicKey(process.env.RECIPIENT_PUBKEY) : null;
+    const receiver = recipient ?? user;
+    const inputs: ClaimInput[] = JSON.parse(process.env.CLAIMS_JSON || '[]');
+    if (inputs.length === 0) {
+        console.error('请设置 CLAIMS_JSON');
+        process.exit(1);
+    }
+
+    // 按合约约定的顺序拼接 remaining_accounts
+    const entries = [];
+    const remainingAccounts: AccountMeta[] = [];
+    for (const input of inputs) {
+        const quest = new PublicKey(input.quest);
+        // eslint-disable-next-line @typescript-eslint/no-explicit-any
+        const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+        const shardIdLe = Buffer.alloc(2);
+        shardIdLe.writeUInt16LE(Math.floor(input.index / 65536));
+        const [bitmapShard] = PublicKey.findProgramAddressSync(
+            [Buffer.from('bitmap'), quest.toBuffer(), shardIdLe],
+            program.programId
+        );
+        remainingAccounts.push(
+            { pubkey: quest, isSigner: false, isWritable: true },
+            { pubkey: questAccount.vaultAuthority, isSigner: false, isWritable: true },
+            { pubkey: bitmapShard, isSigner: false, isWritable: true }
+        );
+        const mint: PublicKey = questAccount.mint;
+        if (!mint.equals(PublicKey.default)) {
+            const mintInfo = await connection.getAccountInfo(mint);
+            if (!mintInfo) throw new Error('mint not found');
+            const tokenProgram = mintInfo.owner;
+            const userAta = await getAssociatedTokenAddress(mint, receiver, true, tokenProgram);
+            remainingAccounts.push(
+                { pubkey: questAccount.vault, isSigner: false, isWritable: true },
+                { pubkey: mint, isSigner: false, isWritable: false },
+                { pubkey: userAta, isSigner: false, isWritable: true },
+                { pubkey: tokenProgram, isSigner: false, isWritable: false }
+            );
+        }
+        entries.push({
+            quest,
+            index: new anchor.BN(input.index),
+            amount: new anchor.BN(input.amount),
+            proof: input.proof.map(hexTo32ByteArray),
+        });
+    }
+
+    try {
+        const sig = await (program.methods as any)
+            .claimMany(entries)
+            .accounts({
+                user,
+                recipient,
+                associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 1_000_000 })])
+            .rpc();
+        console.log(`claimed ${entries.length} quests:`, sig);
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+})();
@@ -0,0 +1,80 @@
+import 'dotenv/config';
+import * as anchor from '@coral-xyz/anchor';
+import { PublicKey, Connection, Keypair } from '@solana/web3.js';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress } from '@solana/spl-token';
+import { hexTo32ByteArray } from '../../utils/merkle';
+
+// vesting / 累计分发 quest 的领取，可多次执行：
+// vesting 下 AMOUNT 为叶子中的总分配额，发放已解锁部分；累计分发下 AMOUNT 为当前 root 中的累计应得总额
+(async () => {
+    const connection = new Connection(process.env.RPC_URL || 'http://127.0.0.1:8899', 'confirmed');
+    const secret = new Uint8Array(JSON.parse(process.env.USER_SECRET_JSON || '[]'));
+    const wallet = new anchor.Wallet(Keypair.fromSecretKey(secret));
+    const provider = new anchor.AnchorProvider(connection, wallet, { commitment: 'confirmed' });
+    anchor.setProvider(provider);
+
+    // eslint-disable-next-line @typescript-eslint/no-var-requires
+    const idl = require('../../target/idl/sharely_contract.json');
+    const program = new anchor.Program(idl as anchor.Idl, provider);
+
+    const quest = new PublicKey(process.env.QUEST_PUBKEY || '');
+    const index = Number(process.env.INDEX || '0');
+    const amount = new anchor.BN(process.env.AMOUNT || '0');
+    const proof: number[][] = JSON.parse(process.env.PROOF_JSON || '[]').map(hexTo32ByteArray);
+    const user = wallet.publicKey;
+
+    const [claimRecord] = PublicKey.findProgramAddressSync(
+        [Buffer.from('claim'), quest.toBuffer(), user.toBuffer()],
+        program.programId
+    );
+    const [vaultAuthority] = PublicKey.findProgramAddressSync(
+        [Buffer.from('vault_auth'), quest.toBuffer()],
+        program.programId
+    );
+
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const questAccount = await (program.account as any)["questAccount"].fetch(quest);
+    // eslint-disable-next-line @typescript-eslint/no-explicit-any
+    const record = await (program.account as any)["claimRecord"].fetchNullable(claimRecord);
+    const cumulative = 'cumulative' in questAccount.claimMode;
+    if (cumulative) {
+        console.log('cumulative root version =', questAccount.version);
+    } else {
+        console.log('vesting:', questAccount.cliffAt.toString(), questAccount.vestingStart.toString(), questAccount.vestingEnd.toString());
+    }
+    console.log('already claimed =', record ? record.claimedAmount.toString() : '0');
+
+    const mint: PublicKey = questAccount.mint;
+    let tokenAccounts = {
+        vault: null as PublicKey | null,
+        userAta: null as PublicKey | null,
+        mint: null as PublicKey | null,
+        tokenProgram: null as PublicKey | null,
+        associatedTokenProgram: null as PublicKey | null,
+    };
+    if (!mint.equals(PublicKey.default)) {
+        const mintInfo = await connection.getAccountInfo(mint);
+        if (!mintInfo) throw new Error('mint not found');
+        const tokenProgram = mintInfo.owner;
+        const userAta = await getAssociatedTokenAddress(mint, user, true, tokenProgram);
+        tokenAccounts = { vault: questAccount.vault, userAta, mint, tokenProgram, associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID };
+    }
+
+    try {
+        const method = cumulative ? 'claimCumulative' : 'claimVested';
+        const sig = await (program.methods as any)[method](new anchor.BN(index), amount, proof)
+            .accounts({
+                user,
+                quest,
+                vaultAuthority,
+                ...tokenAccounts,
+                claimRecord,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            } as any)
+            .rpc();
+        console.log(`${method} tx:`, sig);
+    } catch (error) {
+        console.error('操作失败:', error);
+        process.exit(1);
+    }
+})();
@@ -0,0 +1,56 @@
+import { Keypair, PublicKey } from '@solana/web3.js';
+import { sha256 } from '@noble/hashes/sha2';
+import bs58 from 'bs58';
+import dotenv from 'dotenv';
+
+dotenv.config();
+
+function parseSecret(json: string | undefined): Uint8Array {
+    if (!json) throw new Error('missing USER_SECRET_JSON');
+    return new Uint8Array(JSON.parse(json));
+}
+
+function u64ToLeBytes(n: bigint): Uint8Array {
+    const buf = new Uint8Array(8);
+    new DataView(buf.buffer).setBigUint64(0, n, true);
+    return buf;
+}
+
+function i64ToLeBytes(n: bigint): Uint8Array {
+    const buf = new Uint8Array(8);
+    new DataView(buf.buffer).setBigInt64(0, n, true);
+    return buf;
+}
+
+// 用户离线签署代付领取授权，无需持有 SOL；输出交给 relayer 提交 claim_for
+(async () => {
+    const user = Keypair.fromSecretKey(parseSecret(process.env.USER_SECRET_JSON));
+
+    const programId = new PublicKey(process.env.PROGRAM_ID || '');
+    const quest = new PublicKey(process.env.QUEST_PUBKEY || '');
+    const index = BigInt(process.env.INDEX || '0');
+    const amount = BigInt(process.env.AMOUNT || '0');
+    // 收款钱包，未设置时为用户自己
+    const recipient = new PublicKey(process.env.RECIPIENT_PUBKEY || user.publicKey.toBase58());
+    const expiresAt = BigInt(process.env.CLAIM_EXPIRES_AT || String(Math.floor(Date.now() / 1000) + 3600));
+
+    // 字段顺序与链上 ClaimAuthorization 一致
+    const domain = sha256(new TextEncoder().encode('sharely:claim:v1'));
+    const message = new Uint8Array([
+        ...domain,
+        ...programId.toBytes(),
+        ...quest.toBytes(),
+        ...u64ToLeBytes(index),
+        ...u64ToLeBytes(amount),
+        ...recipient.toBytes(),
+        ...i64ToLeBytes(expiresAt),
+    ]);
+
+    const nacl = await import('tweetnacl');
+    const sig = nacl.default.sign.detached(message, user.secretKey);
+
+    console.log('USER_PUBKEY=', user.publicKey.toBase58());
+    console.log('RECIPIENT_PUBKEY=', recipient.toBase58());
+    console.log('CLAIM_MESSAGE_BASE58=', bs58.encode(message));
+    console.log('CLAIM_SIGNATURE_BASE58=', bs58.encode(sig));
+})();
@@ -12,22 +12,44 @@ export function u64ToLeBytes(x: bigint): Uint8Array {
     return out;
 }
 
-export function leafHash(index: bigint, user: PublicKey, amount: bigint): Buffer {
+// 与合约 LeafFormat 对应：v1 为旧格式；v2 叶子绑定 quest 地址，叶子/内部节点分别加 0x00/0x01 前缀
+export type LeafFormat = 'v1' | 'v2';
+
+const LEAF_PREFIX = Buffer.from([0x00]);
+const NODE_PREFIX = Buffer.from([0x01]);
+
+export function leafHash(
+    index: bigint,
+    user: PublicKey,
+    amount: bigint,
+    format: LeafFormat = 'v1',
+    quest?: PublicKey
+): Buffer {
     const idx = u64ToLeBytes(index);
     const amt = u64ToLeBytes(amount);
-    const data = Buffer.concat([Buffer.from(idx), Buffer.from(user.toBytes()), Buffer.from(amt)]);
-    return Buffer.from(sha256(data));
+    const parts = [Buffer.from(idx), Buffer.from(user.toBytes()), Buffer.from(amt)];
+    if (format === 'v2') {
+        if (!quest) throw new Error('v2 leaf requires quest pubkey');
+        parts.unshift(LEAF_PREFIX, Buffer.from(quest.toBytes()));
+    }
+    return Buffer.from(sha256(Buffer.concat(parts)));
 }
 
-export function hashPairSorted(a: Buffer, b: Buffer): Buffer {
+export function hashPairSorted(a: Buffer, b: Buffer, format: LeafFormat = 'v1'): Buffer {
     const [x, y] = Buffer.compare(a, b) <= 0 ? [a, b] : [b, a];
-    return Buffer.from(sha256(Buffer.concat([x, y])));
+    const parts = format === 'v2' ? [NODE_PREFIX, x, y] : [x, y];
+    return Buffer.from(sha256(Buffer.concat(parts)));
+}
+
+// 转为 activate_quest 的 leaf_format 参数
+export function leafFormatArg(format: LeafFormat) {
+    return format === 'v2' ? { v2: {} } : { v1: {} };
 }
 
-export function buildMerkle(leaves: Buffer[]): MerkleTree {
+export function buildMerkle(leaves: Buffer[], format: LeafFormat = 'v1'): MerkleTree {
     const hashFn = (data: Buffer) => {
         const half = data.length / 2;
-        return hashPairSorted(data.subarray(0, half), data.subarray(half));
+        return hashPairSorted(data.subarray(0, half), data.subarray(half), format);
     };
     return new MerkleTree(leaves, hashFn, { isBitcoinTree: false, sortPairs: false });
 }